@@ -2,8 +2,9 @@ if exists('b:current_syntax')
   finish
 endif
 
-syn keyword ralKeywords instruments score init perf print println output local skipwhite
-syn keyword ralTypes Int Float Audio String skipwhite
+syn keyword ralKeywords settings tables instruments score init perf print println output outch send receive always local if else while for in skipwhite
+syn keyword ralTypes Int Float Audio Control String Bool Table skipwhite
+syn keyword ralBoolean true false
 
 syn keyword ralTodo TODO FIXME NOTES NOTE XXX contained
 syn match ralComment "//.*$" contains=ralTodo
@@ -18,6 +19,7 @@ hi def link ralTodo Todo
 hi def link ralComment Comment
 hi def link ralString String
 hi def link ralNumber Number
+hi def link ralBoolean Boolean
 hi def link ralKeywords Keyword
 hi def link ralTypes Type
 
@@ -1,4 +1,8 @@
 pub mod audio_buffer;
 pub mod components;
+pub mod file_format;
+pub mod file_writer;
+pub mod resample;
 pub mod shared_audio_buffer;
 pub mod stream;
+pub mod table;
@@ -1,6 +1,6 @@
 use crate::utils::number_array::NumberArray;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct AudioBuffer {
     channels: usize,
     buffer_size: usize,
@@ -59,7 +59,7 @@ impl AudioBuffer {
     pub fn add_sample(&mut self, channel: usize, sample: usize, value: f32) {
         self.data[channel][sample] += value;
     }
-    
+
     pub fn add_from(&mut self, source: &AudioBuffer) {
         assert!(self.buffer_size == source.buffer_size);
 
@@ -96,6 +96,24 @@ impl AudioBuffer {
         }
     }
 
+    /// Samples divided by 0 become 0, like dividing numbers by 0.
+    pub fn divide_by(&mut self, other: &AudioBuffer) {
+        assert!(self.buffer_size == other.buffer_size());
+
+        for channel in 0..self.channels {
+            if channel < other.channels() {
+                for sample in 0..self.buffer_size {
+                    let divisor = other.get_sample(channel, sample);
+                    self.data[channel][sample] = if divisor == 0.0 {
+                        0.0
+                    } else {
+                        self.data[channel][sample] / divisor
+                    };
+                }
+            }
+        }
+    }
+
     pub fn apply_gain(&mut self, gain: f32) {
         for channel in 0..self.channels {
             for sample in 0..self.buffer_size {
@@ -6,16 +6,31 @@ pub struct StreamInfo {
     pub sample_rate: u32,
     pub buffer_size: usize,
     pub channels: usize,
+    // length of the event being processed in samples, usize::MAX for always on instruments
+    pub event_duration: usize,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ComponentType {
     Generator,
+    Processor {
+        audio_inputs: usize,
+        audio_outputs: usize,
+    },
 }
 
 pub trait Component: DynClone {
-    fn arg_count(&self) -> usize;
     fn component_type(&self) -> ComponentType;
     fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value>;
+
+    /// Given the literal passed to each of the component's constant inputs during compilation,
+    /// returns an error if the value can't be used.
+    fn constant_input(&mut self, _position: usize, _value: &Value) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Called once the sample rate is known, before the performance, so buffers aren't allocated on the audio thread.
+    fn prepare(&mut self, _sample_rate: u32) {}
 }
 
 clone_trait_object!(Component);
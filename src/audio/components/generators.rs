@@ -1,8 +1,20 @@
 pub mod generator;
 
 pub mod adsr;
+pub mod expseg;
+pub mod fm4;
+pub mod fm_op;
+pub mod lag;
+pub mod line;
+pub mod linseg;
 pub mod mtof;
 pub mod noise;
 pub mod oscil;
 pub mod padsr;
-pub mod sample;
\ No newline at end of file
+pub mod phasor;
+pub mod portamento;
+pub mod sample;
+pub mod sampler;
+pub mod table_oscil;
+pub mod transeg;
+pub mod vco;
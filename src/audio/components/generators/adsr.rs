@@ -17,58 +17,85 @@ impl Adsr {
 }
 
 impl Component for Adsr {
-    fn arg_count(&self) -> usize {
-        Self::INPUT_TYPES.len()
-    }
-
     fn component_type(&self) -> ComponentType {
         ComponentType::Generator
     }
 
     fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
-        let output;
+        let sr = stream_info.sample_rate as f32;
+        let envelope = Envelope {
+            attack: args[0].get_float() * sr,
+            decay: args[1].get_float() * sr,
+            sustain_level: args[2].get_float(),
+            release: args[3].get_float() * sr,
+            // the release ends with the event unless a duration is given
+            total: args
+                .get(4)
+                .map_or(stream_info.event_duration as f32, |total| {
+                    total.get_float() * sr
+                }),
+        };
+
+        // the level at the start of the next block too, so it can be interpolated over this one
+        let start = envelope.level(self.sample_clock);
+        self.sample_clock += stream_info.buffer_size as f32;
+        let end = envelope.level(self.sample_clock);
 
-        let attack = args[0].get_float() * stream_info.sample_rate as f32;
-        let decay = args[1].get_float() * stream_info.sample_rate as f32;
-        let sustain_level = args[2].get_float();
-        let release = args[3].get_float() * stream_info.sample_rate as f32;
-        let total = args[4].get_float() * stream_info.sample_rate as f32;
+        vec![Value::control(start, end)]
+    }
+}
 
-        if self.sample_clock < attack {
+/// Stage lengths in samples.
+struct Envelope {
+    attack: f32,
+    decay: f32,
+    sustain_level: f32,
+    release: f32,
+    total: f32,
+}
+
+impl Envelope {
+    fn level(&self, sample_clock: f32) -> f32 {
+        let Envelope {
+            attack,
+            decay,
+            sustain_level,
+            release,
+            total,
+        } = *self;
+
+        if sample_clock < attack {
             // attack phase
-            output = self.sample_clock / attack;
-        } else if (self.sample_clock - attack) < decay {
+            sample_clock / attack
+        } else if (sample_clock - attack) < decay {
             // decay phase
-            let base = self.sample_clock - attack;
+            let base = sample_clock - attack;
             let level = 1.0 - (base / decay);
-            output = sustain_level + ((1.0 - sustain_level) * level);
-        } else if (self.sample_clock >= attack + decay) && (self.sample_clock < total - release) {
+            sustain_level + ((1.0 - sustain_level) * level)
+        } else if (sample_clock >= attack + decay) && (sample_clock < total - release) {
             // sustain phase
-            output = sustain_level;
-        } else if (self.sample_clock >= total - release)
-            && (self.sample_clock - (total - release) < release)
+            sustain_level
+        } else if (sample_clock >= total - release) && (sample_clock - (total - release) < release)
         {
             // release phase
-            let base = self.sample_clock - (total - release);
+            let base = sample_clock - (total - release);
             let level = 1.0 - (base / release);
-            output = sustain_level * level;
+            sustain_level * level
         } else {
             // after release
-            output = 0.0;
+            0.0
         }
-
-        self.sample_clock += stream_info.buffer_size as f32;
-        vec![Value::float(output)]
     }
 }
 
 impl Generator<5> for Adsr {
-    const INPUT_TYPES: [VariableType; 5] = [
-        VariableType::Float,
-        VariableType::Float,
-        VariableType::Float,
-        VariableType::Float,
-        VariableType::Float,
+    const INPUT_TYPES: [&'static [VariableType]; 5] = [
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
     ];
-    const OUTPUT_TYPE: VariableType = VariableType::Float;
+    const OUTPUT_TYPE: VariableType = VariableType::Control;
+    const OPTIONAL_INPUTS: usize = 1;
 }
@@ -0,0 +1,42 @@
+use super::{
+    generator::Generator,
+    linseg::{Curve, Segments},
+};
+use crate::audio::components::component::{Component, ComponentType, StreamInfo};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// Exponential breakpoint envelope, which sounds more natural for amplitudes and frequencies than `Linseg`.
+/// Args: start value, then any number of pairs of duration and value.
+/// Segments to or from 0, or changing sign, are linear since an exponential can't get there.
+#[derive(Clone)]
+pub struct Expseg {
+    segments: Segments,
+}
+
+impl Generator<3> for Expseg {
+    const INPUT_TYPES: [&'static [VariableType]; 3] = [
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+    const REPEATED_INPUTS: usize = 2;
+}
+
+impl Expseg {
+    pub fn new() -> Self {
+        Expseg {
+            segments: Segments::new(Curve::Exponential),
+        }
+    }
+}
+
+impl Component for Expseg {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        vec![Value::audio(self.segments.process(stream_info, &args))]
+    }
+}
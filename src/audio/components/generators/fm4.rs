@@ -0,0 +1,128 @@
+use super::{fm_op::Operator, generator::Generator};
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+const OPERATORS: usize = 4;
+
+/// Operators are numbered 1 to 4 as on a TX81Z, index 0 here is operator 1.
+/// For each algorithm, the operators modulating each operator as a bitmask, and the carriers.
+/// Operators are only modulated by higher numbered ones, so running 4 down to 1 works in one pass.
+const ALGORITHMS: [([u8; OPERATORS], u8); 8] = [
+    // 4 -> 3 -> 2 -> 1
+    ([0b0010, 0b0100, 0b1000, 0], 0b0001),
+    // (3 + 4) -> 2 -> 1
+    ([0b0010, 0b1100, 0, 0], 0b0001),
+    // (2 + 4) -> 1, 3 -> 2
+    ([0b1010, 0b0100, 0, 0], 0b0001),
+    // (2 + 3) -> 1, 4 -> 3
+    ([0b0110, 0, 0b1000, 0], 0b0001),
+    // 2 -> 1, 4 -> 3
+    ([0b0010, 0, 0b1000, 0], 0b0101),
+    // 4 -> 1, 2 and 3
+    ([0b1000, 0b1000, 0b1000, 0], 0b0111),
+    // 4 -> 3, 1 and 2 unmodulated
+    ([0, 0, 0b1000, 0], 0b0111),
+    // all carriers
+    ([0, 0, 0, 0], 0b1111),
+];
+
+/// Four operator FM voice. Args: amp, freq, algorithm, the ratio of each operator,
+/// the level of each operator and the feedback of operator 4.
+/// Carrier levels set their volume in the mix, modulator levels are their modulation index in radians.
+#[derive(Clone)]
+pub struct Fm4 {
+    operators: [Operator; OPERATORS],
+}
+
+impl Generator<12> for Fm4 {
+    const INPUT_TYPES: [&'static [VariableType]; 12] = [
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Int],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+}
+
+impl Fm4 {
+    pub fn new() -> Self {
+        Fm4 {
+            operators: [
+                Operator::new(),
+                Operator::new(),
+                Operator::new(),
+                Operator::new(),
+            ],
+        }
+    }
+}
+
+impl Component for Fm4 {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+
+        let (modulators, carriers) = match usize::try_from(args[2].get_int())
+            .ok()
+            .and_then(|algorithm| ALGORITHMS.get(algorithm))
+        {
+            Some(algorithm) => *algorithm,
+            None => {
+                eprintln!("No fm algorithm for integer {}", args[2].get_int());
+                return vec![Value::audio(buffer)];
+            }
+        };
+
+        // keep the mix level the same whichever algorithm is used
+        let carrier_gain = 1.0 / carriers.count_ones() as f32;
+
+        for sample in 0..stream_info.buffer_size {
+            let freq = args[1].get_sample(sample);
+            let mut outputs = [0.0; OPERATORS];
+            let mut mix = 0.0;
+
+            for op in (0..OPERATORS).rev() {
+                let modulation = (0..OPERATORS)
+                    .filter(|other| modulators[op] & (1 << other) != 0)
+                    .map(|other| outputs[other])
+                    .sum();
+                let feedback = if op == OPERATORS - 1 {
+                    args[11].get_sample(sample)
+                } else {
+                    0.0
+                };
+
+                let ratio = args[3 + op].get_sample(sample);
+                let level = args[3 + OPERATORS + op].get_sample(sample);
+                outputs[op] = self.operators[op].next(
+                    freq * ratio,
+                    stream_info.sample_rate,
+                    modulation,
+                    feedback,
+                ) * level;
+
+                if carriers & (1 << op) != 0 {
+                    mix += outputs[op];
+                }
+            }
+
+            buffer.set_sample(0, sample, mix * carrier_gain * args[0].get_sample(sample));
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
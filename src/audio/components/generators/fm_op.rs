@@ -0,0 +1,86 @@
+use std::f32::consts::PI;
+
+use super::{generator::Generator, phasor::Phasor};
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// A sine operator whose phase can be pushed around by other operators and by its own output.
+#[derive(Clone)]
+pub struct Operator {
+    phasor: Phasor,
+    // the last two outputs, averaged for feedback to stop it from oscillating at nyquist
+    history: [f32; 2],
+}
+
+impl Operator {
+    pub fn new() -> Self {
+        Operator {
+            phasor: Phasor::new(),
+            history: [0.0; 2],
+        }
+    }
+
+    /// Returns the next sample between -1 and 1. Modulation and feedback are in radians.
+    pub fn next(&mut self, freq: f32, sample_rate: u32, modulation: f32, feedback: f32) -> f32 {
+        let feedback = feedback * (self.history[0] + self.history[1]) * 0.5;
+        let output = (self.phasor.phase() * PI * 2.0 + modulation + feedback).sin();
+
+        self.history = [output, self.history[0]];
+        self.phasor.advance(freq, sample_rate);
+        output
+    }
+}
+
+/// Single phase modulation operator. Args: amp, freq, ratio, modulation, feedback.
+/// The operator runs at freq * ratio, and the modulation input is added to its phase in radians,
+/// so the amp of the operator feeding it is the modulation index.
+#[derive(Clone)]
+pub struct FmOp {
+    operator: Operator,
+}
+
+impl Generator<5> for FmOp {
+    const INPUT_TYPES: [&'static [VariableType]; 5] = [
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+}
+
+impl FmOp {
+    pub fn new() -> Self {
+        FmOp {
+            operator: Operator::new(),
+        }
+    }
+}
+
+impl Component for FmOp {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+
+        for sample in 0..stream_info.buffer_size {
+            let freq = args[1].get_sample(sample) * args[2].get_sample(sample);
+            let value = self.operator.next(
+                freq,
+                stream_info.sample_rate,
+                args[3].get_sample(sample),
+                args[4].get_sample(sample),
+            );
+
+            buffer.set_sample(0, sample, value * args[0].get_sample(sample));
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
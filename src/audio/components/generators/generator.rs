@@ -1,6 +1,19 @@
-use crate::{audio::components::component::Component, runtime::instrument::VariableType};
+use crate::{
+    audio::components::component::{Component, ComponentType},
+    runtime::instrument::VariableType,
+};
 
 pub trait Generator<const ARG_COUNT: usize>: Component {
-    const INPUT_TYPES: [VariableType; ARG_COUNT];
+    const INPUT_TYPES: [&'static [VariableType]; ARG_COUNT];
     const OUTPUT_TYPE: VariableType;
+    /// How many of the last inputs form a group that can be repeated, like the segments of an envelope.
+    const REPEATED_INPUTS: usize = 0;
+    /// How many of the last inputs can be left out, in which case the component uses its default for them.
+    const OPTIONAL_INPUTS: usize = 0;
+    /// Inputs that must be given a literal, which is passed to `Component::constant_input` during compilation.
+    const CONSTANT_INPUTS: &'static [usize] = &[];
+    /// The positions of the file path and resample quality inputs of components that play sample files,
+    /// so the compiler can load them before the performance.
+    const SAMPLE_INPUTS: Option<(usize, usize)> = None;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Generator;
 }
@@ -0,0 +1,56 @@
+use super::generator::Generator;
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// One pole lowpass for smoothing stepped values. Args: input, time in seconds to get 63% of the way to a new value.
+#[derive(Clone)]
+pub struct Lag {
+    // None until the first sample, so the output starts at the input instead of gliding up from 0
+    value: Option<f32>,
+}
+
+impl Generator<2> for Lag {
+    const INPUT_TYPES: [&'static [VariableType]; 2] = [
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+}
+
+impl Lag {
+    pub fn new() -> Self {
+        Lag { value: None }
+    }
+}
+
+impl Component for Lag {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+        let sr = stream_info.sample_rate as f32;
+
+        for sample in 0..stream_info.buffer_size {
+            let input = args[0].get_sample(sample);
+            let time = args[1].get_sample(sample);
+
+            let value = match self.value {
+                Some(value) if time > 0.0 => {
+                    let coefficient = (-1.0 / (time * sr)).exp();
+                    input + (value - input) * coefficient
+                }
+                _ => input,
+            };
+
+            buffer.set_sample(0, sample, value);
+            self.value = Some(value);
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
@@ -0,0 +1,56 @@
+use super::generator::Generator;
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// Straight line from one value to another. Args: start, duration in seconds, end.
+/// Unlike `Linseg` it keeps going at the same slope past the end.
+#[derive(Clone)]
+pub struct Line {
+    sample_clock: usize,
+}
+
+impl Generator<3> for Line {
+    const INPUT_TYPES: [&'static [VariableType]; 3] = [
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+}
+
+impl Line {
+    pub fn new() -> Self {
+        Line { sample_clock: 0 }
+    }
+}
+
+impl Component for Line {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+
+        let start = args[0].get_float();
+        let length = args[1].get_float() * stream_info.sample_rate as f32;
+        let end = args[2].get_float();
+
+        for sample in 0..stream_info.buffer_size {
+            let value = if length > 0.0 {
+                start + (end - start) * self.sample_clock as f32 / length
+            } else {
+                // a line with no length is already at its end
+                end
+            };
+
+            buffer.set_sample(0, sample, value);
+            self.sample_clock += 1;
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
@@ -0,0 +1,212 @@
+use super::generator::Generator;
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Curve {
+    Linear,
+    Exponential,
+    // each segment has a curvature between its duration and value, like Csound's transeg
+    Shaped,
+}
+
+impl Curve {
+    /// The number of args describing each segment.
+    fn segment_args(&self) -> usize {
+        match self {
+            Curve::Linear | Curve::Exponential => 2,
+            Curve::Shaped => 3,
+        }
+    }
+}
+
+/// Position in a breakpoint envelope, kept between blocks so each sample only looks at the segment it's in.
+#[derive(Clone)]
+pub struct Segments {
+    curve: Curve,
+    segment: usize,
+    // samples since the start of the current segment, fractional since segments needn't be a whole number of samples
+    elapsed: f64,
+}
+
+impl Segments {
+    pub fn new(curve: Curve) -> Self {
+        Segments {
+            curve,
+            segment: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Fills a block from breakpoint args: a starting value, then for each segment its duration in seconds,
+    /// its curvature for shaped curves, and the value to move to. Holds the last value once the segments run out.
+    pub fn process(&mut self, stream_info: &StreamInfo, args: &[Value]) -> SharedAudioBuffer {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+        for sample in 0..stream_info.buffer_size {
+            buffer.set_sample(0, sample, self.next(stream_info.sample_rate, args));
+        }
+
+        buffer
+    }
+
+    fn next(&mut self, sample_rate: u32, args: &[Value]) -> f32 {
+        let segment_args = self.curve.segment_args();
+        let segments = (args.len() - 1) / segment_args;
+
+        let value = loop {
+            // the value each segment starts from is the last arg before it
+            let first_arg = 1 + self.segment * segment_args;
+            if self.segment >= segments {
+                break args[first_arg - 1].get_float();
+            }
+
+            let length = args[first_arg].get_float().max(0.0) as f64 * sample_rate as f64;
+            if self.elapsed < length {
+                let from = args[first_arg - 1].get_float();
+                let to = args[first_arg + segment_args - 1].get_float();
+                let curvature = match self.curve {
+                    Curve::Shaped => args[first_arg + 1].get_float(),
+                    _ => 0.0,
+                };
+
+                break interpolate(
+                    from,
+                    to,
+                    (self.elapsed / length) as f32,
+                    self.curve,
+                    curvature,
+                );
+            }
+
+            self.elapsed -= length;
+            self.segment += 1;
+        };
+
+        self.elapsed += 1.0;
+        value
+    }
+}
+
+fn interpolate(from: f32, to: f32, fraction: f32, curve: Curve, curvature: f32) -> f32 {
+    match curve {
+        // exponential curves can't reach or cross 0, so those segments are linear
+        Curve::Exponential if from * to > 0.0 => from * (to / from).powf(fraction),
+        // positive curvatures start slowly and finish quickly, negative ones the opposite
+        Curve::Shaped if curvature.abs() > 1e-6 => {
+            from + (to - from) * (1.0 - (fraction * curvature).exp()) / (1.0 - curvature.exp())
+        }
+        _ => from + (to - from) * fraction,
+    }
+}
+
+/// Linear breakpoint envelope. Args: start value, then any number of pairs of duration and value.
+#[derive(Clone)]
+pub struct Linseg {
+    segments: Segments,
+}
+
+impl Generator<3> for Linseg {
+    const INPUT_TYPES: [&'static [VariableType]; 3] = [
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+    const REPEATED_INPUTS: usize = 2;
+}
+
+impl Linseg {
+    pub fn new() -> Self {
+        Linseg {
+            segments: Segments::new(Curve::Linear),
+        }
+    }
+}
+
+impl Component for Linseg {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        vec![Value::audio(self.segments.process(stream_info, &args))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(curve: Curve, args: &[f32], block_size: usize, blocks: usize) -> Vec<f32> {
+        let args: Vec<Value> = args.iter().map(|arg| Value::float(*arg)).collect();
+        let stream_info = StreamInfo {
+            sample_rate: 10,
+            buffer_size: block_size,
+            channels: 1,
+            event_duration: usize::MAX,
+        };
+
+        let mut segments = Segments::new(curve);
+        let mut output = Vec::new();
+        for _ in 0..blocks {
+            let buffer = segments.process(&stream_info, &args);
+            output.extend((0..block_size).map(|sample| buffer.get_sample(0, sample)));
+        }
+        output
+    }
+
+    fn assert_close(output: &[f32], expected: &[f32]) {
+        assert_eq!(output.len(), expected.len());
+        for (value, expected) in output.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "{output:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn linear_segments_then_hold() {
+        // at 10Hz, 0 to 1 over 4 samples then down to 0.5 over 2
+        let output = render(Curve::Linear, &[0.0, 0.4, 1.0, 0.2, 0.5], 3, 3);
+        assert_close(&output, &[0.0, 0.25, 0.5, 0.75, 1.0, 0.75, 0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn block_size_doesnt_change_the_envelope() {
+        let args = [1.0, 0.35, 0.0, 0.0, 2.0, 0.55, 0.5];
+        let whole = render(Curve::Linear, &args, 12, 1);
+        let blocks = render(Curve::Linear, &args, 1, 12);
+        assert_eq!(whole, blocks);
+    }
+
+    #[test]
+    fn zero_length_segments_jump() {
+        let output = render(Curve::Linear, &[0.0, 0.0, 1.0, 0.2, 0.0], 4, 1);
+        assert_close(&output, &[1.0, 0.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn exponential_falls_back_to_linear_through_zero() {
+        let output = render(Curve::Exponential, &[1.0, 0.2, 4.0, 0.2, 0.0], 4, 1);
+        assert_close(&output, &[1.0, 2.0, 4.0, 2.0]);
+    }
+
+    #[test]
+    fn shaped_curvature() {
+        let linear = render(Curve::Linear, &[0.0, 1.0, 1.0], 10, 1);
+        let flat = render(Curve::Shaped, &[0.0, 1.0, 0.0, 1.0], 10, 1);
+        assert_close(&flat, &linear);
+
+        // positive curvature starts slowly, negative starts quickly
+        let slow = render(Curve::Shaped, &[0.0, 1.0, 4.0, 1.0], 10, 1);
+        let fast = render(Curve::Shaped, &[0.0, 1.0, -4.0, 1.0], 10, 1);
+        for sample in 1..10 {
+            assert!(slow[sample] < linear[sample]);
+            assert!(fast[sample] > linear[sample]);
+        }
+    }
+}
@@ -8,10 +8,6 @@ use super::generator::Generator;
 pub struct Mtof;
 
 impl Component for Mtof {
-    fn arg_count(&self) -> usize {
-        Self::INPUT_TYPES.len()
-    }
-
     fn component_type(&self) -> ComponentType {
         ComponentType::Generator
     }
@@ -23,6 +19,6 @@ impl Component for Mtof {
 }
 
 impl Generator<1> for Mtof {
-    const INPUT_TYPES: [VariableType; 1] = [VariableType::Int];
+    const INPUT_TYPES: [&'static [VariableType]; 1] = [&[VariableType::Int]];
     const OUTPUT_TYPE: VariableType = VariableType::Float;
 }
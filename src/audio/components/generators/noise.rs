@@ -1,9 +1,9 @@
 use rand::{rngs::ThreadRng, Rng};
 
 use super::generator::Generator;
+use crate::audio::components::component::Component;
 use crate::audio::components::component::{ComponentType, StreamInfo};
 use crate::audio::shared_audio_buffer::SharedAudioBuffer;
-use crate::audio::components::component::Component;
 use crate::runtime::instrument::VariableType;
 use crate::runtime::value::Value;
 
@@ -21,10 +21,6 @@ impl Noise {
 }
 
 impl Component for Noise {
-    fn arg_count(&self) -> usize {
-        Self::INPUT_TYPES.len()
-    }
-
     fn component_type(&self) -> ComponentType {
         ComponentType::Generator
     }
@@ -33,7 +29,7 @@ impl Component for Noise {
         let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
 
         for sample in 0..stream_info.buffer_size {
-            let value = self.rng.gen_range(-1.0..1.0) * args[0].get_float();
+            let value = self.rng.gen_range(-1.0..1.0) * args[0].get_sample(sample);
             buffer.set_sample(0, sample, value);
         }
 
@@ -42,6 +38,6 @@ impl Component for Noise {
 }
 
 impl Generator<1> for Noise {
-    const INPUT_TYPES: [VariableType; 1] = [VariableType::Float];
+    const INPUT_TYPES: [&'static [VariableType]; 1] = [&[VariableType::Float, VariableType::Audio]];
     const OUTPUT_TYPE: VariableType = VariableType::Audio;
 }
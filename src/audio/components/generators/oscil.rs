@@ -1,6 +1,6 @@
 use std::f32::consts::PI;
 
-use super::generator::Generator;
+use super::{generator::Generator, phasor::Phasor};
 use crate::audio::{
     components::component::{Component, ComponentType, StreamInfo},
     shared_audio_buffer::SharedAudioBuffer,
@@ -16,25 +16,27 @@ pub enum Shape {
 
 #[derive(Clone)]
 pub struct Oscil {
-    phase: f32,
+    phasor: Phasor,
 }
 
 impl Generator<3> for Oscil {
-    const INPUT_TYPES: [VariableType; 3] = [VariableType::Float, VariableType::Float, VariableType::Int];
+    const INPUT_TYPES: [&'static [VariableType]; 3] = [
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Int],
+    ];
     const OUTPUT_TYPE: VariableType = VariableType::Audio;
 }
 
 impl Oscil {
     pub fn new() -> Self {
-        Oscil { phase: 0.0 }
+        Oscil {
+            phasor: Phasor::new(),
+        }
     }
 }
 
 impl Component for Oscil {
-    fn arg_count(&self) -> usize {
-        Self::INPUT_TYPES.len()
-    }
-
     fn component_type(&self) -> ComponentType {
         ComponentType::Generator
     }
@@ -42,8 +44,6 @@ impl Component for Oscil {
     fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
         let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
 
-        let amps = args[0].get_float();
-        let freq = args[1].get_float();
         let shape = match Shape::try_from(args[2].get_int()) {
             Ok(s) => s,
             Err(_) => {
@@ -52,51 +52,30 @@ impl Component for Oscil {
             }
         };
 
-        let sr = stream_info.sample_rate as f32;
-
         for sample in 0..stream_info.buffer_size {
+            let phase = self.phasor.phase();
             let value = match shape {
-                Shape::Sine => {
-                    if self.phase >= 1.0 {
-                        self.phase = 0.0;
-                    }
-                    let output = (self.phase * PI * 2.0).sin();
-                    self.phase += 1.0 / (sr / freq);
-                    output
-                }
-                Shape::Saw => {
-                    if self.phase >= 1.0 {
-                        self.phase = -1.0;
-                    }
-                    let output = self.phase;
-                    self.phase += 1.0 / (sr / freq) * 2.0;
-                    output
-                }
+                Shape::Sine => (phase * PI * 2.0).sin(),
+                Shape::Saw => phase * 2.0 - 1.0,
                 Shape::Square => {
-                    if self.phase >= 1.0 {
-                        self.phase = 0.0
-                    }
-                    self.phase += 1.0 / (sr / freq);
-                    if self.phase < 0.5 {
+                    if phase < 0.5 {
                         -1.0
                     } else {
                         1.0
                     }
                 }
                 Shape::Tri => {
-                    if self.phase >= 1.0 {
-                        self.phase = 0.0;
-                    }
-                    self.phase += 1.0 / (sr / freq);
-                    if self.phase < 0.5 {
-                        (self.phase - 0.25) * 4.0
+                    if phase < 0.5 {
+                        (phase - 0.25) * 4.0
                     } else {
-                        ((1.0 - self.phase) - 0.25) * 4.0
+                        ((1.0 - phase) - 0.25) * 4.0
                     }
                 }
             };
 
-            buffer.set_sample(0, sample, value * amps);
+            buffer.set_sample(0, sample, value * args[0].get_sample(sample));
+            self.phasor
+                .advance(args[1].get_sample(sample), stream_info.sample_rate);
         }
 
         vec![Value::audio(buffer)]
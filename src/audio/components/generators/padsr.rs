@@ -20,10 +20,6 @@ impl Padsr {
 }
 
 impl Component for Padsr {
-    fn arg_count(&self) -> usize {
-        Self::INPUT_TYPES.len()
-    }
-
     fn component_type(&self) -> ComponentType {
         ComponentType::Generator
     }
@@ -31,13 +27,20 @@ impl Component for Padsr {
     fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
         let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
 
-        let attack = args[0].get_float() * stream_info.sample_rate as f32;
-        let decay = args[1].get_float() * stream_info.sample_rate as f32;
-        let sustain_level = args[2].get_float();
-        let release = args[3].get_float() * stream_info.sample_rate as f32;
-        let total = args[4].get_float() * stream_info.sample_rate as f32;
+        let sr = stream_info.sample_rate as f32;
+        // the release ends with the event unless a duration is given
+        let total = args
+            .get(4)
+            .map_or(stream_info.event_duration as f32, |total| {
+                total.get_float() * sr
+            });
 
         for sample in 0..stream_info.buffer_size {
+            let attack = args[0].get_sample(sample) * sr;
+            let decay = args[1].get_sample(sample) * sr;
+            let sustain_level = args[2].get_sample(sample);
+            let release = args[3].get_sample(sample) * sr;
+
             if self.sample_clock < attack {
                 // attack phase
                 buffer.set_sample(0, sample, self.sample_clock / attack);
@@ -45,11 +48,7 @@ impl Component for Padsr {
                 // decay phase
                 let base = self.sample_clock - attack;
                 let level = 1.0 - (base / decay);
-                buffer.set_sample(
-                    0,
-                    sample,
-                    sustain_level + ((1.0 - sustain_level) * level),
-                );
+                buffer.set_sample(0, sample, sustain_level + ((1.0 - sustain_level) * level));
             } else if (self.sample_clock >= attack + decay) && (self.sample_clock < total - release)
             {
                 // sustain phase
@@ -73,12 +72,13 @@ impl Component for Padsr {
 }
 
 impl Generator<5> for Padsr {
-    const INPUT_TYPES: [VariableType; 5] = [
-        VariableType::Float,
-        VariableType::Float,
-        VariableType::Float,
-        VariableType::Float,
-        VariableType::Float,
+    const INPUT_TYPES: [&'static [VariableType]; 5] = [
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float],
     ];
     const OUTPUT_TYPE: VariableType = VariableType::Audio;
+    const OPTIONAL_INPUTS: usize = 1;
 }
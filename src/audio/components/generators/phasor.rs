@@ -0,0 +1,20 @@
+/// Phase in [0, 1) that advances by a frequency each sample, so changing the frequency never makes it jump.
+#[derive(Clone)]
+pub struct Phasor {
+    phase: f32,
+}
+
+impl Phasor {
+    pub fn new() -> Self {
+        Phasor { phase: 0.0 }
+    }
+
+    pub fn phase(&self) -> f32 {
+        self.phase
+    }
+
+    /// Moves on by one sample at the given frequency, which can be negative to run backwards.
+    pub fn advance(&mut self, freq: f32, sample_rate: u32) {
+        self.phase = (self.phase + freq / sample_rate as f32).rem_euclid(1.0);
+    }
+}
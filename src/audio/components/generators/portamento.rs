@@ -0,0 +1,75 @@
+use super::generator::Generator;
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// Glides in a straight line to each new input value. Args: input, time in seconds each glide takes.
+#[derive(Clone)]
+pub struct Portamento {
+    // None until the first sample, so the output starts at the input instead of gliding up from 0
+    value: Option<f32>,
+    target: f32,
+    // per sample, worked out when the target changes so the glide is linear
+    step: f32,
+}
+
+impl Generator<2> for Portamento {
+    const INPUT_TYPES: [&'static [VariableType]; 2] = [
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+}
+
+impl Portamento {
+    pub fn new() -> Self {
+        Portamento {
+            value: None,
+            target: 0.0,
+            step: 0.0,
+        }
+    }
+}
+
+impl Component for Portamento {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+        let sr = stream_info.sample_rate as f32;
+
+        for sample in 0..stream_info.buffer_size {
+            let input = args[0].get_sample(sample);
+            let time = args[1].get_sample(sample);
+
+            let value = match self.value {
+                Some(value) if time > 0.0 => {
+                    if input != self.target {
+                        self.target = input;
+                        self.step = (input - value) / (time * sr);
+                    }
+
+                    // stop at the target rather than overshooting it
+                    if (self.target - value).abs() <= self.step.abs() {
+                        self.target
+                    } else {
+                        value + self.step
+                    }
+                }
+                _ => {
+                    self.target = input;
+                    input
+                }
+            };
+
+            buffer.set_sample(0, sample, value);
+            self.value = Some(value);
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
@@ -1,76 +1,170 @@
 use crate::{
     audio::{
         components::component::{Component, ComponentType, StreamInfo},
+        resample::{self, ResampleQuality},
         shared_audio_buffer::SharedAudioBuffer,
     },
     runtime::{instrument::VariableType, value::Value},
 };
 use sndfile::{self, OpenOptions, ReadOptions, SndFileIO};
-use std::{cell::OnceCell, collections::HashMap, sync::Mutex};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::OnceLock,
+};
 
 use super::generator::Generator;
 
-static SAMPLE_LOOKUP: Mutex<OnceCell<HashMap<String, (usize, Vec<f32>)>>> =
-    Mutex::new(OnceCell::new());
+// files are converted to the stream's sample rate when the performance starts, so are cached per rate and quality
+type SampleVersions = HashMap<(u32, ResampleQuality), (usize, Vec<f32>)>;
+
+// set once before the performance starts so the audio thread can read it without locking,
+// keyed by path first so it can be looked up without building an owned key
+static SAMPLE_LOOKUP: OnceLock<HashMap<String, SampleVersions>> = OnceLock::new();
+
+/// A file as it was read from disk, before conversion to the stream's sample rate.
+#[derive(Clone)]
+pub struct SampleFile {
+    channels: usize,
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+impl SampleFile {
+    pub fn load(path: &String) -> Result<Self, String> {
+        let mut snd = OpenOptions::ReadOnly(ReadOptions::Auto)
+            .from_path(path)
+            .map_err(|err| format!("Failed to open file {path}: {err:?}"))?;
+
+        let samples: Vec<f32> = snd
+            .read_all_to_vec()
+            .map_err(|err| format!("Failed to load {path}: {err:?}"))?;
+
+        Ok(SampleFile {
+            channels: snd.get_channels(),
+            sample_rate: snd.get_samplerate() as u32,
+            samples,
+        })
+    }
+
+    pub fn channel(&self, channel: usize) -> Vec<f32> {
+        self.samples
+            .iter()
+            .skip(channel)
+            .step_by(self.channels.max(1))
+            .copied()
+            .collect()
+    }
+}
+
+/// Converts every loaded file to the stream's sample rate at each quality used by the program.
+/// Only the first call has any effect.
+pub fn prepare_samples(
+    files: &HashMap<String, SampleFile>,
+    qualities: &HashSet<ResampleQuality>,
+    sample_rate: u32,
+) {
+    SAMPLE_LOOKUP.get_or_init(|| {
+        let mut sample_lookup = HashMap::new();
+        for (path, file) in files {
+            let mut versions = SampleVersions::new();
+            for quality in qualities {
+                let samples = resample::resample(
+                    &file.samples,
+                    file.channels,
+                    file.sample_rate,
+                    sample_rate,
+                    *quality,
+                );
+
+                println!(
+                    "Loaded file {path} with {quality:?} resampling, {} samples",
+                    samples.len()
+                );
+                versions.insert((sample_rate, *quality), (file.channels, samples));
+            }
+
+            sample_lookup.insert(path.clone(), versions);
+        }
+
+        sample_lookup
+    });
+}
+
+/// Gets the channel count and interleaved samples of a file prepared by `prepare_samples`.
+pub fn sample_data(
+    path: &str,
+    sample_rate: u32,
+    quality: ResampleQuality,
+) -> Option<(usize, &'static [f32])> {
+    SAMPLE_LOOKUP
+        .get()?
+        .get(path)?
+        .get(&(sample_rate, quality))
+        .map(|(channels, samples)| (*channels, samples.as_slice()))
+}
 
 #[derive(Clone)]
 pub struct Sample {
     index: usize,
+    // only report a missing file once rather than on every block
+    reported_missing: bool,
 }
 
 impl Sample {
     pub fn new() -> Self {
-        let sl = SAMPLE_LOOKUP.lock().unwrap();
-        sl.get_or_init(|| HashMap::new());
-
-        Sample { index: 0 }
+        Sample {
+            index: 0,
+            reported_missing: false,
+        }
     }
 }
 
 impl Component for Sample {
-    fn arg_count(&self) -> usize {
-        Self::INPUT_TYPES.len()
-    }
-
     fn component_type(&self) -> ComponentType {
         ComponentType::Generator
     }
 
     fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
         let sample_path = args[0].get_string();
+        // the quality can be left out, which plays the file with linear resampling
+        let quality = match args
+            .get(1)
+            .map(|quality| ResampleQuality::try_from(quality.get_int()))
+        {
+            None => ResampleQuality::Linear,
+            Some(Ok(quality)) => quality,
+            Some(Err(_)) => {
+                eprintln!("No resample quality for integer {}", args[1].get_int());
+                return vec![Value::audio(SharedAudioBuffer::new(
+                    1,
+                    stream_info.buffer_size,
+                ))];
+            }
+        };
 
-        let mut sample_lookup = SAMPLE_LOOKUP.lock().unwrap();
-        let sample_lookup = sample_lookup.get_mut().unwrap();
-
-        if !sample_lookup.contains_key(sample_path) {
-            // new sample, load it in
-            match OpenOptions::ReadOnly(ReadOptions::Auto).from_path(sample_path) {
-                Ok(mut snd) => {
-                    let samples: Vec<f32> = match snd.read_all_to_vec() {
-                        Ok(samples) => samples,
-                        Err(err) => {
-                            eprintln!("Failed to load {}: {:?}", sample_path, err);
-                            vec![]
-                        }
-                    };
-        
-                    println!("Opened file {sample_path}, read {} samples", samples.len());
-                    sample_lookup.insert(sample_path.clone(), (snd.get_channels(), samples));
-                }
-                Err(err) => {
-                    eprintln!("Failed to open file {sample_path}: {:?}", err);
-                    return vec![Value::audio(SharedAudioBuffer::new(1, stream_info.buffer_size))];
+        let (channels, samples) = match sample_data(sample_path, stream_info.sample_rate, quality) {
+            Some(data) => data,
+            None => {
+                if !self.reported_missing {
+                    eprintln!("{sample_path} wasn't loaded before the performance started");
+                    self.reported_missing = true;
                 }
+
+                return vec![Value::audio(SharedAudioBuffer::new(
+                    1,
+                    stream_info.buffer_size,
+                ))];
             }
-        }
+        };
 
-        let (channels, samples) = sample_lookup.get(sample_path).unwrap();
-        let mut output =
-            vec![Value::audio(SharedAudioBuffer::new(1, stream_info.buffer_size)); *channels];
+        // cloning a SharedAudioBuffer shares its data, so each channel needs its own
+        let mut output: Vec<Value> = (0..channels)
+            .map(|_| Value::audio(SharedAudioBuffer::new(1, stream_info.buffer_size)))
+            .collect();
 
         // this handles interleaved??
         'outer: for sample in 0..stream_info.buffer_size {
-            for channel in 0..*channels {
+            for channel in 0..channels {
                 if self.index >= samples.len() {
                     break 'outer;
                 }
@@ -86,7 +180,10 @@ impl Component for Sample {
     }
 }
 
-impl Generator<1> for Sample {
-    const INPUT_TYPES: [VariableType; 1] = [VariableType::String];
+impl Generator<2> for Sample {
+    const INPUT_TYPES: [&'static [VariableType]; 2] =
+        [&[VariableType::String], &[VariableType::Int]];
     const OUTPUT_TYPE: VariableType = VariableType::Audio;
+    const OPTIONAL_INPUTS: usize = 1;
+    const SAMPLE_INPUTS: Option<(usize, usize)> = Some((0, 1));
 }
@@ -0,0 +1,240 @@
+use crate::{
+    audio::{
+        components::component::{Component, ComponentType, StreamInfo},
+        resample::ResampleQuality,
+        shared_audio_buffer::SharedAudioBuffer,
+    },
+    runtime::{instrument::VariableType, value::Value},
+};
+
+use super::{generator::Generator, sample};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PlaybackMode {
+    OneShot = 0,
+    Loop = 1,
+}
+
+pub enum PlaybackModeError {
+    OutOfBounds,
+}
+
+impl TryFrom<i64> for PlaybackMode {
+    type Error = PlaybackModeError;
+    fn try_from(value: i64) -> Result<PlaybackMode, PlaybackModeError> {
+        match value {
+            0 => Ok(PlaybackMode::OneShot),
+            1 => Ok(PlaybackMode::Loop),
+            _ => Err(PlaybackModeError::OutOfBounds),
+        }
+    }
+}
+
+/// Plays a file at a variable rate, optionally reversed and looping between two points.
+/// Args: path, resample quality, rate, start offset, mode, reverse, and optionally loop start, loop end and crossfade.
+/// Times are in seconds, a loop end of 0 means the end of the file,
+/// and the start offset counts back from the end when reversed.
+/// Without the optional args a looping sampler loops the whole file with no crossfade.
+#[derive(Clone)]
+pub struct Sampler {
+    // in frames, None until the first block when the file's length is known
+    position: Option<f64>,
+    start: f64,
+    // only report a missing file once rather than on every block
+    reported_missing: bool,
+}
+
+impl Sampler {
+    pub fn new() -> Self {
+        Sampler {
+            position: None,
+            start: 0.0,
+            reported_missing: false,
+        }
+    }
+}
+
+/// Linearly interpolated read of one channel at a fractional frame, silent outside the file.
+fn read_frame(samples: &[f32], channels: usize, channel: usize, position: f64) -> f32 {
+    let frames = samples.len() / channels;
+    if position < 0.0 || position >= frames as f64 {
+        return 0.0;
+    }
+
+    let index = position as usize;
+    let fraction = (position - index as f64) as f32;
+    let current = samples[index * channels + channel];
+    let next = if index + 1 < frames {
+        samples[(index + 1) * channels + channel]
+    } else {
+        0.0
+    };
+
+    current + (next - current) * fraction
+}
+
+impl Component for Sampler {
+    fn component_type(&self) -> ComponentType {
+        Self::COMPONENT_TYPE
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let silence = || {
+            vec![Value::audio(SharedAudioBuffer::new(
+                1,
+                stream_info.buffer_size,
+            ))]
+        };
+
+        let quality = match ResampleQuality::try_from(args[1].get_int()) {
+            Ok(quality) => quality,
+            Err(_) => {
+                eprintln!("No resample quality for integer {}", args[1].get_int());
+                return silence();
+            }
+        };
+
+        let mode = match PlaybackMode::try_from(args[4].get_int()) {
+            Ok(mode) => mode,
+            Err(_) => {
+                eprintln!("No sampler playback mode for integer {}", args[4].get_int());
+                return silence();
+            }
+        };
+
+        let reverse = args[5].get_bool();
+        let sr = stream_info.sample_rate as f64;
+
+        let sample_path = args[0].get_string();
+        let (channels, samples) =
+            match sample::sample_data(sample_path, stream_info.sample_rate, quality) {
+                Some(data) => data,
+                None => {
+                    if !self.reported_missing {
+                        eprintln!("{sample_path} wasn't loaded before the performance started");
+                        self.reported_missing = true;
+                    }
+
+                    return silence();
+                }
+            };
+
+        let mut output: Vec<Value> = (0..channels)
+            .map(|_| Value::audio(SharedAudioBuffer::new(1, stream_info.buffer_size)))
+            .collect();
+
+        let frames = (samples.len() / channels.max(1)) as f64;
+        if frames == 0.0 {
+            return output;
+        }
+
+        if self.position.is_none() {
+            let start_offset = (args[3].get_float() as f64 * sr).clamp(0.0, frames - 1.0);
+            self.start = if reverse {
+                frames - 1.0 - start_offset
+            } else {
+                start_offset
+            };
+            self.position = Some(self.start);
+        }
+
+        let start = self.start;
+        let position = self.position.as_mut().unwrap();
+
+        let seconds = |index: usize| {
+            args.get(index)
+                .map_or(0.0, |arg| arg.get_float() as f64 * sr)
+        };
+        let loop_start = seconds(6).clamp(0.0, frames);
+        let loop_end = match seconds(7) {
+            end if end <= 0.0 => frames,
+            end => end.min(frames),
+        };
+        let loop_length = loop_end - loop_start;
+        // a start offset past the loop plays out without looping
+        let looping = mode == PlaybackMode::Loop
+            && loop_length >= 1.0
+            && if reverse {
+                start >= loop_start
+            } else {
+                start < loop_end
+            };
+
+        // the crossfade blends in audio from the other side of the loop point,
+        // so it can't be longer than the audio available there
+        let crossfade = if reverse {
+            frames - loop_end
+        } else {
+            loop_start
+        }
+        .min(loop_length)
+        .min(seconds(8))
+        .max(0.0);
+
+        for sample in 0..stream_info.buffer_size {
+            if looping {
+                if !reverse && *position >= loop_end {
+                    *position = loop_start + (*position - loop_start) % loop_length;
+                } else if reverse && *position < loop_start {
+                    *position = loop_end - (loop_start - *position) % loop_length;
+                }
+            }
+
+            let fade = if looping && crossfade > 0.0 {
+                if !reverse && *position >= loop_end - crossfade && *position < loop_end {
+                    Some((
+                        (*position - (loop_end - crossfade)) / crossfade,
+                        *position - loop_length,
+                    ))
+                } else if reverse && *position < loop_start + crossfade && *position >= loop_start {
+                    Some((
+                        (loop_start + crossfade - *position) / crossfade,
+                        *position + loop_length,
+                    ))
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            for (channel, buffer) in output.iter_mut().enumerate() {
+                let mut value = read_frame(samples, channels, channel, *position);
+                if let Some((amount, other_position)) = fade {
+                    // equal power, the two sides of the loop are usually uncorrelated
+                    let amount = amount as f32 * std::f32::consts::FRAC_PI_2;
+                    value = value * amount.cos()
+                        + read_frame(samples, channels, channel, other_position) * amount.sin();
+                }
+
+                buffer.get_audio_mut().set_sample(0, sample, value);
+            }
+
+            let rate = args[2].get_sample(sample).max(0.0) as f64;
+            if reverse {
+                *position -= rate;
+            } else {
+                *position += rate;
+            }
+        }
+
+        output
+    }
+}
+
+impl Generator<9> for Sampler {
+    const INPUT_TYPES: [&'static [VariableType]; 9] = [
+        &[VariableType::String],
+        &[VariableType::Int],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float],
+        &[VariableType::Int],
+        &[VariableType::Bool],
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+    const OPTIONAL_INPUTS: usize = 3;
+    const SAMPLE_INPUTS: Option<(usize, usize)> = Some((0, 1));
+}
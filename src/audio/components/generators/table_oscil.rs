@@ -0,0 +1,50 @@
+use super::generator::Generator;
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// Loops through a table at the given frequency, with linear interpolation between its samples.
+#[derive(Clone)]
+pub struct TableOscil {
+    phase: f32,
+}
+
+impl Generator<3> for TableOscil {
+    const INPUT_TYPES: [&'static [VariableType]; 3] = [
+        &[VariableType::Table],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+}
+
+impl TableOscil {
+    pub fn new() -> Self {
+        TableOscil { phase: 0.0 }
+    }
+}
+
+impl Component for TableOscil {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+
+        let table = args[0].get_table();
+        let len = table.len() as f32;
+        let sr = stream_info.sample_rate as f32;
+
+        for sample in 0..stream_info.buffer_size {
+            let value = table.read_wrapped(self.phase * len);
+            buffer.set_sample(0, sample, value * args[1].get_sample(sample));
+
+            self.phase = (self.phase + args[2].get_sample(sample) / sr).rem_euclid(1.0);
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
@@ -0,0 +1,43 @@
+use super::{
+    generator::Generator,
+    linseg::{Curve, Segments},
+};
+use crate::audio::components::component::{Component, ComponentType, StreamInfo};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// Breakpoint envelope with a curvature for each segment. Args: start value, then any number of groups of
+/// duration, curvature and value. A curvature of 0 is linear, positive ones start slowly and finish quickly,
+/// and negative ones start quickly and ease in to the value.
+#[derive(Clone)]
+pub struct Transeg {
+    segments: Segments,
+}
+
+impl Generator<4> for Transeg {
+    const INPUT_TYPES: [&'static [VariableType]; 4] = [
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+        &[VariableType::Float],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+    const REPEATED_INPUTS: usize = 3;
+}
+
+impl Transeg {
+    pub fn new() -> Self {
+        Transeg {
+            segments: Segments::new(Curve::Shaped),
+        }
+    }
+}
+
+impl Component for Transeg {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        vec![Value::audio(self.segments.process(stream_info, &args))]
+    }
+}
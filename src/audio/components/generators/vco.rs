@@ -0,0 +1,123 @@
+use std::f32::consts::PI;
+
+use super::{generator::Generator, oscil::Shape, phasor::Phasor};
+use crate::audio::{
+    components::component::{Component, ComponentType, StreamInfo},
+    shared_audio_buffer::SharedAudioBuffer,
+};
+use crate::runtime::{instrument::VariableType, value::Value};
+
+/// Band limited oscillator, with the same shapes as `Oscil` but PolyBLEP corrections
+/// at each discontinuity and PolyBLAMP corrections at each corner so high notes don't alias.
+/// The pulse width is the fraction of each cycle a square spends high.
+#[derive(Clone)]
+pub struct Vco {
+    phasor: Phasor,
+}
+
+impl Generator<4> for Vco {
+    const INPUT_TYPES: [&'static [VariableType]; 4] = [
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Int],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const OUTPUT_TYPE: VariableType = VariableType::Audio;
+}
+
+impl Vco {
+    pub fn new() -> Self {
+        Vco {
+            phasor: Phasor::new(),
+        }
+    }
+}
+
+/// Polynomial approximation of the band limited step residual, for a discontinuity at phase 0.
+fn poly_blep(phase: f32, increment: f32) -> f32 {
+    if phase < increment {
+        let t = phase / increment;
+        t + t - t * t - 1.0
+    } else if phase > 1.0 - increment {
+        let t = (phase - 1.0) / increment;
+        t * t + t + t + 1.0
+    } else {
+        0.0
+    }
+}
+
+/// Polynomial approximation of the band limited ramp residual, for a change of slope at phase 0.
+/// Scaled by the change in slope per sample, it smooths the corner the way `poly_blep` smooths a step.
+fn poly_blamp(phase: f32, increment: f32) -> f32 {
+    if phase < increment {
+        let t = 1.0 - phase / increment;
+        t * t * t / 6.0
+    } else if phase > 1.0 - increment {
+        let t = 1.0 + (phase - 1.0) / increment;
+        t * t * t / 6.0
+    } else {
+        0.0
+    }
+}
+
+/// Low for the start of the cycle and high for the last `width` of it, so a width of 0.5 matches `Oscil`'s square.
+fn pulse(phase: f32, increment: f32, width: f32) -> f32 {
+    let naive = if phase < 1.0 - width { -1.0 } else { 1.0 };
+    naive - poly_blep(phase, increment) + poly_blep((phase + width) % 1.0, increment)
+}
+
+/// Falls from 1 at phase 0.5 to -1 at phase 0 and back, the same as `Oscil`'s triangle.
+fn triangle(phase: f32, increment: f32) -> f32 {
+    let naive = if phase < 0.5 {
+        (phase - 0.25) * 4.0
+    } else {
+        ((1.0 - phase) - 0.25) * 4.0
+    };
+
+    // the slope changes by 8 per cycle at each corner, turning up at phase 0 and down at 0.5
+    let slope_change = 8.0 * increment;
+    naive
+        + slope_change * (poly_blamp(phase, increment) - poly_blamp((phase + 0.5) % 1.0, increment))
+}
+
+impl Component for Vco {
+    fn component_type(&self) -> ComponentType {
+        ComponentType::Generator
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+
+        let shape = match Shape::try_from(args[2].get_int()) {
+            Ok(s) => s,
+            Err(_) => {
+                eprintln!("No vco shape for integer {}", args[2].get_int());
+                return vec![Value::audio(buffer)];
+            }
+        };
+
+        let nyquist = stream_info.sample_rate as f32 / 2.0;
+
+        for sample in 0..stream_info.buffer_size {
+            let freq = args[1].get_sample(sample).clamp(0.0, nyquist);
+            let increment = freq / stream_info.sample_rate as f32;
+            let phase = self.phasor.phase();
+
+            let value = match shape {
+                Shape::Sine => (phase * PI * 2.0).sin(),
+                Shape::Saw => 2.0 * phase - 1.0 - poly_blep(phase, increment),
+                Shape::Square => {
+                    // keep both edges at least a sample apart
+                    let width = args[3].get_sample(sample).clamp(increment, 1.0 - increment);
+                    pulse(phase, increment, width)
+                }
+                Shape::Tri => triangle(phase, increment),
+            };
+
+            buffer.set_sample(0, sample, value * args[0].get_sample(sample));
+            self.phasor.advance(freq, stream_info.sample_rate);
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
@@ -0,0 +1,6 @@
+pub mod processor;
+
+pub mod biquad;
+pub mod delay;
+pub mod peak;
+pub mod reverb;
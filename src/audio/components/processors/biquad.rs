@@ -0,0 +1,211 @@
+use std::f32::consts::PI;
+
+use crate::{
+    audio::{
+        components::{
+            component::{Component, ComponentType, StreamInfo},
+            processors::processor::Processor,
+        },
+        shared_audio_buffer::SharedAudioBuffer,
+    },
+    runtime::{
+        instrument::VariableType,
+        value::{Value, ValueType},
+    },
+};
+
+#[derive(Clone, Copy)]
+pub enum FilterType {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+    Peak,
+}
+
+/// Coefficients and state for a single biquad section, using the formulae from the RBJ audio EQ cookbook.
+#[derive(Clone)]
+pub struct BiquadState {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl BiquadState {
+    pub fn new() -> Self {
+        BiquadState {
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    pub fn set_coefficients(
+        &mut self,
+        filter_type: FilterType,
+        sample_rate: f32,
+        cutoff: f32,
+        q: f32,
+        gain_db: f32,
+    ) {
+        // keep the filter stable for silly values
+        let cutoff = cutoff.clamp(10.0, sample_rate * 0.49);
+        let q = q.max(0.01);
+
+        let w0 = 2.0 * PI * cutoff / sample_rate;
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * q);
+
+        let (b0, b1, b2, a0, a1, a2) = match filter_type {
+            FilterType::LowPass => (
+                (1.0 - cos_w0) / 2.0,
+                1.0 - cos_w0,
+                (1.0 - cos_w0) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos_w0,
+                1.0 - alpha,
+            ),
+            FilterType::HighPass => (
+                (1.0 + cos_w0) / 2.0,
+                -(1.0 + cos_w0),
+                (1.0 + cos_w0) / 2.0,
+                1.0 + alpha,
+                -2.0 * cos_w0,
+                1.0 - alpha,
+            ),
+            FilterType::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha),
+            FilterType::Notch => (
+                1.0,
+                -2.0 * cos_w0,
+                1.0,
+                1.0 + alpha,
+                -2.0 * cos_w0,
+                1.0 - alpha,
+            ),
+            FilterType::Peak => {
+                let a = 10.0f32.powf(gain_db / 40.0);
+                (
+                    1.0 + alpha * a,
+                    -2.0 * cos_w0,
+                    1.0 - alpha * a,
+                    1.0 + alpha / a,
+                    -2.0 * cos_w0,
+                    1.0 - alpha / a,
+                )
+            }
+        };
+
+        self.b0 = b0 / a0;
+        self.b1 = b1 / a0;
+        self.b2 = b2 / a0;
+        self.a1 = a1 / a0;
+        self.a2 = a2 / a0;
+    }
+
+    pub fn process_sample(&mut self, input: f32) -> f32 {
+        // transposed direct form II
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+}
+
+/// Processes a block through the biquad, only recalculating coefficients every sample if a parameter is Audio.
+pub fn process_block(
+    state: &mut BiquadState,
+    filter_type: FilterType,
+    stream_info: &StreamInfo,
+    input: &Value,
+    params: &[Value],
+) -> SharedAudioBuffer {
+    let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+    let sr = stream_info.sample_rate as f32;
+    let audio_rate = params
+        .iter()
+        .any(|param| param.value_type() == ValueType::Audio);
+
+    let gain_db = |sample| {
+        params
+            .get(2)
+            .map_or(0.0, |gain: &Value| gain.get_sample(sample))
+    };
+
+    if !audio_rate {
+        state.set_coefficients(
+            filter_type,
+            sr,
+            params[0].get_sample(0),
+            params[1].get_sample(0),
+            gain_db(0),
+        );
+    }
+
+    for sample in 0..stream_info.buffer_size {
+        if audio_rate {
+            state.set_coefficients(
+                filter_type,
+                sr,
+                params[0].get_sample(sample),
+                params[1].get_sample(sample),
+                gain_db(sample),
+            );
+        }
+
+        let value = state.process_sample(input.get_sample(sample));
+        buffer.set_sample(0, sample, value);
+    }
+
+    buffer
+}
+
+#[derive(Clone)]
+pub struct Biquad {
+    filter_type: FilterType,
+    state: BiquadState,
+}
+
+impl Biquad {
+    pub fn new(filter_type: FilterType) -> Self {
+        Biquad {
+            filter_type,
+            state: BiquadState::new(),
+        }
+    }
+}
+
+impl Component for Biquad {
+    fn component_type(&self) -> ComponentType {
+        Self::COMPONENT_TYPE
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let buffer = process_block(
+            &mut self.state,
+            self.filter_type,
+            stream_info,
+            &args[0],
+            &args[1..],
+        );
+
+        vec![Value::audio(buffer)]
+    }
+}
+
+impl Processor<3> for Biquad {
+    const INPUT_TYPES: [&'static [VariableType]; 3] = [
+        &[VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const AUDIO_INPUTS: usize = 1;
+    const AUDIO_OUTPUTS: usize = 1;
+}
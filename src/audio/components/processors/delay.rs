@@ -0,0 +1,230 @@
+use crate::{
+    audio::{
+        components::{
+            component::{Component, ComponentType, StreamInfo},
+            processors::processor::Processor,
+        },
+        shared_audio_buffer::SharedAudioBuffer,
+    },
+    runtime::{instrument::VariableType, value::Value},
+};
+
+#[derive(Clone, Copy)]
+pub enum Interpolation {
+    Linear = 0,
+    Cubic = 1,
+}
+
+pub enum InterpolationError {
+    OutOfBounds,
+}
+
+impl TryFrom<i64> for Interpolation {
+    type Error = InterpolationError;
+    fn try_from(value: i64) -> Result<Interpolation, InterpolationError> {
+        match value {
+            0 => Ok(Interpolation::Linear),
+            1 => Ok(Interpolation::Cubic),
+            _ => Err(InterpolationError::OutOfBounds),
+        }
+    }
+}
+
+/// Ring buffer of past samples that can be read back at a fractional delay.
+#[derive(Clone)]
+pub struct DelayLine {
+    buffer: Vec<f32>,
+    write_index: usize,
+}
+
+impl DelayLine {
+    pub fn with_length(length: usize) -> Self {
+        DelayLine {
+            buffer: vec![0.0; length + 4],
+            write_index: 0,
+        }
+    }
+
+    /// Reads the line `delay` samples behind the last write, clamped to at least one sample and at most the buffer length.
+    pub fn read(&self, delay: f32, interpolation: Interpolation) -> f32 {
+        let delay = delay.clamp(1.0, (self.buffer.len() - 3) as f32);
+        let index = delay as usize;
+        let fraction = delay - index as f32;
+
+        match interpolation {
+            Interpolation::Linear => {
+                let current = self.sample_ago(index);
+                let next = self.sample_ago(index + 1);
+                current + (next - current) * fraction
+            }
+            Interpolation::Cubic => {
+                // 4 point hermite, the sample after the newest one hasn't been written yet
+                // so below a two sample delay it's extrapolated from the two newest
+                let current = self.sample_ago(index);
+                let next = self.sample_ago(index + 1);
+                let previous = if index > 1 {
+                    self.sample_ago(index - 1)
+                } else {
+                    2.0 * current - next
+                };
+                let after_next = self.sample_ago(index + 2);
+
+                let c1 = 0.5 * (next - previous);
+                let c2 = previous - 2.5 * current + 2.0 * next - 0.5 * after_next;
+                let c3 = 0.5 * (after_next - previous) + 1.5 * (current - next);
+                ((c3 * fraction + c2) * fraction + c1) * fraction + current
+            }
+        }
+    }
+
+    pub fn write(&mut self, value: f32) {
+        self.buffer[self.write_index] = value;
+        self.write_index = (self.write_index + 1) % self.buffer.len();
+    }
+
+    fn sample_ago(&self, samples: usize) -> f32 {
+        let length = self.buffer.len();
+        self.buffer[(self.write_index + length - samples) % length]
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum DelayType {
+    Delay,
+    Comb,
+    Allpass,
+}
+
+/// Args: input, delay time in seconds, feedback, max delay time and interpolation.
+/// The max delay time and interpolation must be literals, so the line can be allocated before the performance.
+#[derive(Clone)]
+pub struct Delay {
+    delay_type: DelayType,
+    delay_line: DelayLine,
+    max_time: f32,
+    interpolation: Interpolation,
+}
+
+impl Delay {
+    pub fn new(delay_type: DelayType) -> Self {
+        Delay {
+            delay_type,
+            delay_line: DelayLine::with_length(0),
+            max_time: 0.0,
+            interpolation: Interpolation::Linear,
+        }
+    }
+}
+
+impl Component for Delay {
+    fn component_type(&self) -> ComponentType {
+        Self::COMPONENT_TYPE
+    }
+
+    fn constant_input(&mut self, position: usize, value: &Value) -> Result<(), String> {
+        match position {
+            3 => {
+                self.max_time = value.get_float();
+                Ok(())
+            }
+            _ => {
+                self.interpolation = Interpolation::try_from(value.get_int()).map_err(|_| {
+                    format!("No delay interpolation for integer {}", value.get_int())
+                })?;
+                Ok(())
+            }
+        }
+    }
+
+    fn prepare(&mut self, sample_rate: u32) {
+        let length = (self.max_time.max(0.0) * sample_rate as f32).ceil() as usize;
+        self.delay_line = DelayLine::with_length(length);
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+        let interpolation = self.interpolation;
+        let sr = stream_info.sample_rate as f32;
+
+        for sample in 0..stream_info.buffer_size {
+            let input = args[0].get_sample(sample);
+            let delayed = self
+                .delay_line
+                .read(args[1].get_sample(sample) * sr, interpolation);
+            // keep feedback from running away
+            let feedback = args[2].get_sample(sample).clamp(-1.0, 1.0);
+
+            let output = match self.delay_type {
+                DelayType::Delay => {
+                    self.delay_line.write(input + delayed * feedback);
+                    delayed
+                }
+                DelayType::Comb => {
+                    let output = input + delayed * feedback;
+                    self.delay_line.write(output);
+                    output
+                }
+                DelayType::Allpass => {
+                    let value = input + delayed * feedback;
+                    self.delay_line.write(value);
+                    delayed - value * feedback
+                }
+            };
+
+            buffer.set_sample(0, sample, output);
+        }
+
+        vec![Value::audio(buffer)]
+    }
+}
+
+impl Processor<5> for Delay {
+    const INPUT_TYPES: [&'static [VariableType]; 5] = [
+        &[VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float],
+        &[VariableType::Int],
+    ];
+    const AUDIO_INPUTS: usize = 1;
+    const AUDIO_OUTPUTS: usize = 1;
+    const CONSTANT_INPUTS: &'static [usize] = &[3, 4];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(length: usize, writes: usize) -> DelayLine {
+        let mut delay_line = DelayLine::with_length(length);
+        for sample in 0..writes {
+            delay_line.write(sample as f32);
+        }
+        delay_line
+    }
+
+    #[test]
+    fn linear_read_between_samples() {
+        let delay_line = ramp(8, 10);
+        assert_eq!(delay_line.read(1.0, Interpolation::Linear), 9.0);
+        assert_eq!(delay_line.read(2.5, Interpolation::Linear), 7.5);
+    }
+
+    #[test]
+    fn cubic_read_below_two_samples() {
+        // a ramp is reproduced exactly by the hermite, so any shift in the read position would show
+        let delay_line = ramp(8, 10);
+        assert_eq!(delay_line.read(1.0, Interpolation::Cubic), 9.0);
+        assert_eq!(delay_line.read(1.5, Interpolation::Cubic), 8.5);
+        assert_eq!(delay_line.read(2.25, Interpolation::Cubic), 7.75);
+    }
+
+    #[test]
+    fn cubic_read_across_the_wrap() {
+        // the buffer holds 12 samples, so after 30 writes the points straddle the end of it
+        let delay_line = ramp(8, 30);
+        for delay in [3.5, 6.75, 8.5] {
+            assert_eq!(delay_line.read(delay, Interpolation::Cubic), 30.0 - delay);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{
+    audio::components::{
+        component::{Component, ComponentType, StreamInfo},
+        processors::processor::Processor,
+    },
+    runtime::{instrument::VariableType, value::Value},
+};
+
+use super::biquad::{process_block, BiquadState, FilterType};
+
+/// Peaking EQ, which needs a gain in decibels on top of the cutoff and Q of the other biquads.
+#[derive(Clone)]
+pub struct Peak {
+    state: BiquadState,
+}
+
+impl Peak {
+    pub fn new() -> Self {
+        Peak {
+            state: BiquadState::new(),
+        }
+    }
+}
+
+impl Component for Peak {
+    fn component_type(&self) -> ComponentType {
+        Self::COMPONENT_TYPE
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let buffer = process_block(
+            &mut self.state,
+            FilterType::Peak,
+            stream_info,
+            &args[0],
+            &args[1..],
+        );
+
+        vec![Value::audio(buffer)]
+    }
+}
+
+impl Processor<4> for Peak {
+    const INPUT_TYPES: [&'static [VariableType]; 4] = [
+        &[VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const AUDIO_INPUTS: usize = 1;
+    const AUDIO_OUTPUTS: usize = 1;
+}
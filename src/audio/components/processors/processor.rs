@@ -0,0 +1,24 @@
+use crate::{
+    audio::components::component::{Component, ComponentType},
+    runtime::instrument::VariableType,
+};
+
+/// A component that takes audio in and puts audio out, such as a filter or an effect.
+/// The first `AUDIO_INPUTS` inputs are always Audio, and the rest are parameters.
+pub trait Processor<const ARG_COUNT: usize>: Component {
+    const INPUT_TYPES: [&'static [VariableType]; ARG_COUNT];
+    const AUDIO_INPUTS: usize;
+    const AUDIO_OUTPUTS: usize;
+    /// How many of the last inputs form a group that can be repeated.
+    const REPEATED_INPUTS: usize = 0;
+    /// How many of the last inputs can be left out.
+    const OPTIONAL_INPUTS: usize = 0;
+    /// Inputs that must be given a literal, such as sizes that are allocated before the performance.
+    const CONSTANT_INPUTS: &'static [usize] = &[];
+    /// The positions of the file path and resample quality inputs, if the processor reads a sample file.
+    const SAMPLE_INPUTS: Option<(usize, usize)> = None;
+    const COMPONENT_TYPE: ComponentType = ComponentType::Processor {
+        audio_inputs: Self::AUDIO_INPUTS,
+        audio_outputs: Self::AUDIO_OUTPUTS,
+    };
+}
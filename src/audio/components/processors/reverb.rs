@@ -0,0 +1,175 @@
+use crate::{
+    audio::{
+        components::{
+            component::{Component, ComponentType, StreamInfo},
+            processors::processor::Processor,
+        },
+        shared_audio_buffer::SharedAudioBuffer,
+    },
+    runtime::{instrument::VariableType, value::Value},
+};
+
+use super::delay::{DelayLine, Interpolation};
+
+// Freeverb tunings, in samples at 44.1kHz
+const COMB_TUNINGS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+const ALLPASS_TUNINGS: [usize; 4] = [556, 441, 341, 225];
+const STEREO_SPREAD: usize = 23;
+
+const FIXED_GAIN: f32 = 0.015;
+const SCALE_WET: f32 = 3.0;
+const SCALE_ROOM: f32 = 0.28;
+const OFFSET_ROOM: f32 = 0.7;
+const SCALE_DAMPING: f32 = 0.4;
+const ALLPASS_FEEDBACK: f32 = 0.5;
+
+/// Feedback comb with a one pole lowpass in the loop, so high frequencies die away faster.
+#[derive(Clone)]
+struct DampedComb {
+    delay_line: DelayLine,
+    length: f32,
+    filter_store: f32,
+}
+
+impl DampedComb {
+    fn new(length: usize) -> Self {
+        DampedComb {
+            delay_line: DelayLine::with_length(length),
+            length: length as f32,
+            filter_store: 0.0,
+        }
+    }
+
+    fn process_sample(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.delay_line.read(self.length, Interpolation::Linear);
+        self.filter_store = output * (1.0 - damping) + self.filter_store * damping;
+        self.delay_line.write(input + self.filter_store * feedback);
+        output
+    }
+}
+
+/// Freeverb's allpass approximation, which differs slightly from the `Allpass` component.
+#[derive(Clone)]
+struct FreeverbAllpass {
+    delay_line: DelayLine,
+    length: f32,
+}
+
+impl FreeverbAllpass {
+    fn new(length: usize) -> Self {
+        FreeverbAllpass {
+            delay_line: DelayLine::with_length(length),
+            length: length as f32,
+        }
+    }
+
+    fn process_sample(&mut self, input: f32) -> f32 {
+        let delayed = self.delay_line.read(self.length, Interpolation::Linear);
+        self.delay_line.write(input + delayed * ALLPASS_FEEDBACK);
+        delayed - input
+    }
+}
+
+#[derive(Clone)]
+struct ReverbChannel {
+    combs: Vec<DampedComb>,
+    allpasses: Vec<FreeverbAllpass>,
+}
+
+impl ReverbChannel {
+    fn new(sample_rate: u32, spread: usize) -> Self {
+        let scale = |tuning: usize| {
+            (((tuning + spread) as f32 * sample_rate as f32 / 44100.0) as usize).max(1)
+        };
+
+        ReverbChannel {
+            combs: COMB_TUNINGS
+                .iter()
+                .map(|tuning| DampedComb::new(scale(*tuning)))
+                .collect(),
+            allpasses: ALLPASS_TUNINGS
+                .iter()
+                .map(|tuning| FreeverbAllpass::new(scale(*tuning)))
+                .collect(),
+        }
+    }
+
+    fn process_sample(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let mut output = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.process_sample(input, feedback, damping))
+            .sum();
+
+        for allpass in &mut self.allpasses {
+            output = allpass.process_sample(output);
+        }
+
+        output
+    }
+}
+
+/// Stereo Freeverb, with the right channel's delays slightly longer than the left's.
+#[derive(Clone)]
+pub struct Reverb {
+    // created in prepare once the sample rate is known
+    channels: Option<[ReverbChannel; 2]>,
+}
+
+impl Reverb {
+    pub fn new() -> Self {
+        Reverb { channels: None }
+    }
+}
+
+impl Component for Reverb {
+    fn component_type(&self) -> ComponentType {
+        Self::COMPONENT_TYPE
+    }
+
+    fn prepare(&mut self, sample_rate: u32) {
+        self.channels = Some([
+            ReverbChannel::new(sample_rate, 0),
+            ReverbChannel::new(sample_rate, STEREO_SPREAD),
+        ]);
+    }
+
+    fn process(&mut self, stream_info: &StreamInfo, args: Vec<Value>) -> Vec<Value> {
+        let mut left = SharedAudioBuffer::new(1, stream_info.buffer_size);
+        let mut right = SharedAudioBuffer::new(1, stream_info.buffer_size);
+
+        let [left_channel, right_channel] = self.channels.as_mut().unwrap();
+
+        for sample in 0..stream_info.buffer_size {
+            let dry_left = args[0].get_sample(sample);
+            let dry_right = args[1].get_sample(sample);
+            let feedback = args[2].get_sample(sample).clamp(0.0, 1.0) * SCALE_ROOM + OFFSET_ROOM;
+            let damping = args[3].get_sample(sample).clamp(0.0, 1.0) * SCALE_DAMPING;
+            let mix = args[4].get_sample(sample).clamp(0.0, 1.0);
+            let wet = mix * SCALE_WET;
+            let dry = 1.0 - mix;
+
+            // both channels are fed the same mono mix, the difference in delay lengths makes it stereo
+            let input = (dry_left + dry_right) * FIXED_GAIN;
+            let wet_left = left_channel.process_sample(input, feedback, damping);
+            let wet_right = right_channel.process_sample(input, feedback, damping);
+
+            left.set_sample(0, sample, wet_left * wet + dry_left * dry);
+            right.set_sample(0, sample, wet_right * wet + dry_right * dry);
+        }
+
+        vec![Value::audio(left), Value::audio(right)]
+    }
+}
+
+impl Processor<5> for Reverb {
+    const INPUT_TYPES: [&'static [VariableType]; 5] = [
+        &[VariableType::Audio],
+        &[VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+        &[VariableType::Float, VariableType::Audio],
+    ];
+    const AUDIO_INPUTS: usize = 2;
+    const AUDIO_OUTPUTS: usize = 2;
+}
@@ -0,0 +1,178 @@
+use libc::c_int;
+use rand::{rngs::ThreadRng, Rng};
+use sndfile_sys::{
+    SF_FORMAT_AIFF, SF_FORMAT_FLAC, SF_FORMAT_FLOAT, SF_FORMAT_OGG, SF_FORMAT_OPUS,
+    SF_FORMAT_PCM_16, SF_FORMAT_PCM_24, SF_FORMAT_PCM_32, SF_FORMAT_VORBIS, SF_FORMAT_WAV,
+};
+use std::{fmt, path::Path, str::FromStr};
+
+// libsndfile has written MP3 since 1.1.0, but sndfile-sys 0.2 doesn't define the constants
+const SF_FORMAT_MPEG: c_int = 0x230000;
+const SF_FORMAT_MPEG_LAYER_III: c_int = 0x0082;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileFormat {
+    Wav,
+    Aiff,
+    Flac,
+    Ogg,
+    Opus,
+    Mp3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SampleFormat {
+    Pcm16,
+    Pcm24,
+    Pcm32,
+    Float,
+}
+
+#[derive(Debug)]
+pub struct FormatError(String);
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl FromStr for FileFormat {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wav" => Ok(FileFormat::Wav),
+            "aiff" | "aif" => Ok(FileFormat::Aiff),
+            "flac" => Ok(FileFormat::Flac),
+            "ogg" => Ok(FileFormat::Ogg),
+            "opus" => Ok(FileFormat::Opus),
+            "mp3" => Ok(FileFormat::Mp3),
+            _ => Err(FormatError(format!("unknown file format '{s}'"))),
+        }
+    }
+}
+
+impl FromStr for SampleFormat {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pcm16" => Ok(SampleFormat::Pcm16),
+            "pcm24" => Ok(SampleFormat::Pcm24),
+            "pcm32" => Ok(SampleFormat::Pcm32),
+            "float" => Ok(SampleFormat::Float),
+            _ => Err(FormatError(format!("unknown sample format '{s}'"))),
+        }
+    }
+}
+
+impl FileFormat {
+    /// Guesses the format from the path's extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(|extension| extension.parse::<FileFormat>().ok())
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Wav => "wav",
+            FileFormat::Aiff => "aiff",
+            FileFormat::Flac => "flac",
+            FileFormat::Ogg => "ogg",
+            FileFormat::Opus => "opus",
+            FileFormat::Mp3 => "mp3",
+        }
+    }
+
+    /// Vorbis, Opus and MP3 are lossy so have no sample format, FLAC only stores integers.
+    pub fn default_sample_format(&self) -> Option<SampleFormat> {
+        match self {
+            FileFormat::Wav | FileFormat::Aiff => Some(SampleFormat::Float),
+            FileFormat::Flac => Some(SampleFormat::Pcm24),
+            FileFormat::Ogg | FileFormat::Opus | FileFormat::Mp3 => None,
+        }
+    }
+
+    /// The sample format the file will be written with, or an error if this format can't store it.
+    pub fn sample_format(
+        &self,
+        sample_format: Option<SampleFormat>,
+    ) -> Result<Option<SampleFormat>, FormatError> {
+        match (self, sample_format) {
+            (FileFormat::Ogg | FileFormat::Opus | FileFormat::Mp3, Some(sample_format)) => {
+                Err(FormatError(format!(
+                    "{} files are lossy and can't use {sample_format:?}",
+                    self.extension()
+                )))
+            }
+            (FileFormat::Flac, Some(SampleFormat::Pcm32 | SampleFormat::Float)) => Err(
+                FormatError("flac files only support pcm16 and pcm24".to_string()),
+            ),
+            _ => Ok(sample_format.or(self.default_sample_format())),
+        }
+    }
+
+    fn major_format(&self) -> c_int {
+        match self {
+            FileFormat::Wav => SF_FORMAT_WAV,
+            FileFormat::Aiff => SF_FORMAT_AIFF,
+            FileFormat::Flac => SF_FORMAT_FLAC,
+            // Opus is stored in an Ogg container like Vorbis
+            FileFormat::Ogg | FileFormat::Opus => SF_FORMAT_OGG,
+            FileFormat::Mp3 => SF_FORMAT_MPEG,
+        }
+    }
+
+    /// The libsndfile format flags for writing this format, its container combined with its encoding.
+    pub fn format_flags(&self, sample_format: Option<SampleFormat>) -> Result<c_int, FormatError> {
+        let subtype_format = match (self, self.sample_format(sample_format)?) {
+            (FileFormat::Ogg, _) => SF_FORMAT_VORBIS,
+            (FileFormat::Opus, _) => SF_FORMAT_OPUS,
+            (FileFormat::Mp3, _) => SF_FORMAT_MPEG_LAYER_III,
+            (_, Some(SampleFormat::Pcm16)) => SF_FORMAT_PCM_16,
+            (_, Some(SampleFormat::Pcm24)) => SF_FORMAT_PCM_24,
+            (_, Some(SampleFormat::Pcm32)) => SF_FORMAT_PCM_32,
+            (_, Some(SampleFormat::Float)) => SF_FORMAT_FLOAT,
+            (_, None) => unreachable!(),
+        };
+
+        Ok(self.major_format() | subtype_format)
+    }
+}
+
+/// Adds triangular (TPDF) dither of one least significant bit when reducing to 16 or 24 bit PCM,
+/// so quantisation error becomes noise instead of distortion. 32 bit PCM is finer than an f32's
+/// mantissa, so there's no quantisation error to dither.
+pub struct Ditherer {
+    lsb: Option<f32>,
+    rng: ThreadRng,
+}
+
+impl Ditherer {
+    pub fn new(sample_format: Option<SampleFormat>) -> Self {
+        let bits = match sample_format {
+            Some(SampleFormat::Pcm16) => Some(16),
+            Some(SampleFormat::Pcm24) => Some(24),
+            _ => None,
+        };
+
+        Ditherer {
+            lsb: bits.map(|bits| 1.0 / 2.0f32.powi(bits - 1)),
+            rng: rand::thread_rng(),
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if let Some(lsb) = self.lsb {
+            for sample in samples.iter_mut() {
+                let noise = self.rng.gen::<f32>() - self.rng.gen::<f32>();
+                // clip so the dither can't push a full scale sample over and wrap around
+                *sample = (*sample + noise * lsb).clamp(-1.0, 1.0 - lsb);
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use libc::c_int;
+use sndfile_sys::{sf_count_t, SFM_WRITE, SF_INFO, SNDFILE};
+use std::{
+    ffi::{CStr, CString},
+    path::Path,
+    ptr,
+};
+
+/// Writes interleaved samples through libsndfile directly, since the sndfile crate's formats don't include Opus or MP3.
+/// The file's header is finalised when the writer is dropped.
+pub struct FileWriter {
+    snd: *mut SNDFILE,
+    channels: usize,
+}
+
+impl FileWriter {
+    /// Creates the file at `path`, `format` is the flags from `FileFormat::format_flags`.
+    pub fn create(
+        path: &Path,
+        format: c_int,
+        sample_rate: u32,
+        channels: usize,
+    ) -> Result<Self, String> {
+        let c_path = path
+            .to_str()
+            .and_then(|path| CString::new(path).ok())
+            .ok_or_else(|| format!("{} isn't a valid path", path.display()))?;
+
+        let mut info = SF_INFO {
+            frames: 0,
+            samplerate: sample_rate as c_int,
+            channels: channels as c_int,
+            format,
+            sections: 0,
+            seekable: 0,
+        };
+
+        let snd = unsafe { sndfile_sys::sf_open(c_path.as_ptr(), SFM_WRITE, &mut info) };
+        if snd.is_null() {
+            // with no file, libsndfile reports the error from the last failed open
+            return Err(format!(
+                "Failed to open {}: {}",
+                path.display(),
+                error_message(ptr::null_mut())
+            ));
+        }
+
+        Ok(FileWriter { snd, channels })
+    }
+
+    /// Writes the whole frames in `samples`, returning the number of frames written.
+    pub fn write(&mut self, samples: &[f32]) -> Result<usize, String> {
+        let frames = (samples.len() / self.channels) as sf_count_t;
+        let written = unsafe { sndfile_sys::sf_writef_float(self.snd, samples.as_ptr(), frames) };
+        if written < frames {
+            Err(error_message(self.snd))
+        } else {
+            Ok(written as usize)
+        }
+    }
+}
+
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        unsafe {
+            sndfile_sys::sf_close(self.snd);
+        }
+    }
+}
+
+fn error_message(snd: *mut SNDFILE) -> String {
+    unsafe { CStr::from_ptr(sndfile_sys::sf_strerror(snd)) }
+        .to_string_lossy()
+        .into_owned()
+}
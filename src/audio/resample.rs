@@ -0,0 +1,116 @@
+use std::f32::consts::PI;
+
+// zero crossings of the sinc either side of the centre, before widening for downsampling
+const SINC_ZERO_CROSSINGS: f32 = 16.0;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ResampleQuality {
+    Linear = 0,
+    Cubic = 1,
+    Sinc = 2,
+}
+
+pub enum ResampleQualityError {
+    OutOfBounds,
+}
+
+impl TryFrom<i64> for ResampleQuality {
+    type Error = ResampleQualityError;
+    fn try_from(value: i64) -> Result<ResampleQuality, ResampleQualityError> {
+        match value {
+            0 => Ok(ResampleQuality::Linear),
+            1 => Ok(ResampleQuality::Cubic),
+            2 => Ok(ResampleQuality::Sinc),
+            _ => Err(ResampleQualityError::OutOfBounds),
+        }
+    }
+}
+
+/// Converts interleaved samples recorded at `from_rate` to `to_rate`.
+pub fn resample(
+    samples: &[f32],
+    channels: usize,
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Vec<f32> {
+    if from_rate == to_rate || channels == 0 || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let in_frames = samples.len() / channels;
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_frames = (in_frames as f64 / ratio).ceil() as usize;
+    let frame = |index: isize, channel: usize| {
+        let index = index.clamp(0, in_frames as isize - 1) as usize;
+        samples[index * channels + channel]
+    };
+
+    // when downsampling the cutoff has to come down to the new nyquist, which widens the kernel
+    let cutoff = (to_rate as f32 / from_rate as f32).min(1.0);
+    let half_width = SINC_ZERO_CROSSINGS / cutoff;
+
+    let mut output = Vec::with_capacity(out_frames * channels);
+    for out_frame in 0..out_frames {
+        let position = out_frame as f64 * ratio;
+        let index = position.floor() as isize;
+        let fraction = (position - index as f64) as f32;
+
+        for channel in 0..channels {
+            let value = match quality {
+                ResampleQuality::Linear => {
+                    let current = frame(index, channel);
+                    let next = frame(index + 1, channel);
+                    current + (next - current) * fraction
+                }
+                ResampleQuality::Cubic => {
+                    let previous = frame(index - 1, channel);
+                    let current = frame(index, channel);
+                    let next = frame(index + 1, channel);
+                    let after_next = frame(index + 2, channel);
+
+                    let c1 = 0.5 * (next - previous);
+                    let c2 = previous - 2.5 * current + 2.0 * next - 0.5 * after_next;
+                    let c3 = 0.5 * (after_next - previous) + 1.5 * (current - next);
+                    ((c3 * fraction + c2) * fraction + c1) * fraction + current
+                }
+                ResampleQuality::Sinc => {
+                    let first = (position - half_width as f64).ceil() as isize;
+                    let last = (position + half_width as f64).floor() as isize;
+                    (first..=last)
+                        .filter(|index| *index >= 0 && (*index as usize) < in_frames)
+                        .map(|index| {
+                            let distance = (position - index as f64) as f32;
+                            frame(index, channel)
+                                * cutoff
+                                * sinc(distance * cutoff)
+                                * blackman(distance / half_width)
+                        })
+                        .sum()
+                }
+            };
+
+            output.push(value);
+        }
+    }
+
+    output
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < f32::EPSILON {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman window over [-1, 1].
+fn blackman(x: f32) -> f32 {
+    if x.abs() > 1.0 {
+        0.0
+    } else {
+        let phase = PI * (x + 1.0);
+        0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos()
+    }
+}
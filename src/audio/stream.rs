@@ -1,11 +1,11 @@
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
-    BufferSize, BuildStreamError, Device, Sample, StreamConfig, SupportedStreamConfig, FromSample,
+    BufferSize, BuildStreamError, Device, FromSample, Sample, StreamConfig, SupportedStreamConfig,
 };
 // use rand::Rng;
 use std::{error::Error, fmt};
 
-use crate::runtime::vm::VM;
+use crate::runtime::vm::{Settings, VM};
 
 #[derive(Debug)]
 pub struct DeviceError(String);
@@ -52,7 +52,7 @@ unsafe impl Send for Stream {}
 impl Stream {
     pub fn new(vm_ref: &VM) -> Result<Self, Box<dyn Error>> {
         let device = get_device()?;
-        let config = get_config(&device)?;
+        let config = get_config(&device, vm_ref.settings())?;
         let channels = config.channels() as usize;
         let err_fn = |err| eprintln!("Stream error: {err}");
 
@@ -60,12 +60,17 @@ impl Stream {
         vm.add_config(config.clone());
         let length = vm.finalise(config.sample_rate());
 
+        let mut stream_config = config.config();
+        if let Some(block_size) = vm_ref.settings().block_size(config.sample_rate().0) {
+            stream_config.buffer_size = BufferSize::Fixed(block_size);
+        }
+
         Ok(Stream {
             length,
-            config: config.config(),
+            config: stream_config.clone(),
             stream: match config.sample_format() {
                 cpal::SampleFormat::I8 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [i8], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<i8>(channels, data, &mut vm)
                     },
@@ -73,7 +78,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::I16 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<i16>(channels, data, &mut vm)
                     },
@@ -81,7 +86,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::I32 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [i32], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<i32>(channels, data, &mut vm)
                     },
@@ -89,7 +94,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::I64 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [i64], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<i64>(channels, data, &mut vm)
                     },
@@ -97,7 +102,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::U8 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [u8], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<u8>(channels, data, &mut vm)
                     },
@@ -105,7 +110,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::U16 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [u16], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<u16>(channels, data, &mut vm)
                     },
@@ -113,7 +118,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::U32 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [u32], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<u32>(channels, data, &mut vm)
                     },
@@ -121,7 +126,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::U64 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [u64], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<u64>(channels, data, &mut vm)
                     },
@@ -129,7 +134,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::F32 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<f32>(channels, data, &mut vm)
                     },
@@ -137,7 +142,7 @@ impl Stream {
                     None,
                 )?,
                 cpal::SampleFormat::F64 => device.build_output_stream(
-                    &config.config(),
+                    &stream_config,
                     move |data: &mut [f64], _: &cpal::OutputCallbackInfo| {
                         Self::audio_callback::<f64>(channels, data, &mut vm)
                     },
@@ -198,14 +203,41 @@ fn get_device() -> Result<Device, Box<dyn Error>> {
     }
 }
 
-fn get_config(device: &Device) -> Result<SupportedStreamConfig, Box<dyn Error>> {
-    let mut configs = device.supported_output_configs()?;
-    Ok(configs
-        .next()
-        .ok_or_else(|| {
-            Box::new(ConfigError(
-                "No output configurations supported".to_string(),
-            ))
-        })?
-        .with_max_sample_rate())
+/// Finds a config that supports the requested sample rate with the requested number of channels,
+/// falling back to the config with the fewest channels above that so the extra channels are left silent.
+fn get_config(
+    device: &Device,
+    settings: &Settings,
+) -> Result<SupportedStreamConfig, Box<dyn Error>> {
+    let mut configs = device.supported_output_configs()?.filter(|config| {
+        settings.sample_rate.map_or(true, |sample_rate| {
+            config.min_sample_rate().0 <= sample_rate && sample_rate <= config.max_sample_rate().0
+        })
+    });
+
+    let config = match settings.channels {
+        Some(channels) => configs
+            .filter(|config| config.channels() >= channels)
+            .min_by_key(|config| config.channels()),
+        None => configs.next(),
+    }
+    .ok_or_else(|| {
+        Box::new(ConfigError(
+            "No output configurations support the requested sample rate and channels".to_string(),
+        ))
+    })?;
+
+    if let Some(channels) = settings.channels {
+        if config.channels() != channels {
+            eprintln!(
+                "WARNING: the output device doesn't support {channels} channels, using {} channels instead",
+                config.channels()
+            );
+        }
+    }
+
+    Ok(match settings.sample_rate {
+        Some(sample_rate) => config.with_sample_rate(cpal::SampleRate(sample_rate)),
+        None => config.with_max_sample_rate(),
+    })
 }
@@ -0,0 +1,307 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{f32::consts::PI, fmt};
+
+use crate::{
+    audio::components::generators::sample::SampleFile,
+    runtime::value::{Value, ValueType},
+};
+
+static EMPTY: Table = Table {
+    samples: Vec::new(),
+};
+
+#[derive(Clone, Copy)]
+pub enum WindowShape {
+    Hann = 0,
+    Hamming = 1,
+    Blackman = 2,
+    Triangle = 3,
+}
+
+pub enum WindowShapeError {
+    OutOfBounds,
+}
+
+impl TryFrom<i64> for WindowShape {
+    type Error = WindowShapeError;
+    fn try_from(value: i64) -> Result<WindowShape, WindowShapeError> {
+        match value {
+            0 => Ok(WindowShape::Hann),
+            1 => Ok(WindowShape::Hamming),
+            2 => Ok(WindowShape::Blackman),
+            3 => Ok(WindowShape::Triangle),
+            _ => Err(WindowShapeError::OutOfBounds),
+        }
+    }
+}
+
+/// A block of samples generated before the performance, like a Csound ftable.
+/// Tables never change once generated, so values refer to them with a static reference.
+pub struct Table {
+    samples: Vec<f32>,
+}
+
+impl Table {
+    pub fn empty() -> &'static Table {
+        &EMPTY
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Linearly interpolated read at a fractional index, clamped to the ends of the table.
+    pub fn read(&self, index: f32) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+
+        let index = index.clamp(0.0, (self.samples.len() - 1) as f32);
+        let current = index as usize;
+        let next = (current + 1).min(self.samples.len() - 1);
+        let fraction = index - current as f32;
+        self.samples[current] + (self.samples[next] - self.samples[current]) * fraction
+    }
+
+    /// Linearly interpolated read at a fractional index that wraps around, for oscillators.
+    pub fn read_wrapped(&self, index: f32) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+
+        let len = self.samples.len();
+        let index = index.rem_euclid(len as f32);
+        let current = (index as usize).min(len - 1);
+        let next = (current + 1) % len;
+        let fraction = index - current as f32;
+        self.samples[current] + (self.samples[next] - self.samples[current]) * fraction
+    }
+
+    /// Runs the named GEN routine with the literal arguments from the tables block.
+    pub fn generate(gen_name: &str, args: &[Value]) -> Result<Table, String> {
+        let samples = match gen_name {
+            "Sines" => {
+                // size, then the amplitude of each harmonic
+                expect_min_args(gen_name, args, 2)?;
+                let size = size_arg(gen_name, args)?;
+                let amplitudes = (1..args.len())
+                    .map(|index| float_arg(gen_name, args, index))
+                    .collect::<Result<Vec<f32>, String>>()?;
+
+                let samples = (0..size)
+                    .map(|index| {
+                        let phase = 2.0 * PI * index as f32 / size as f32;
+                        amplitudes
+                            .iter()
+                            .enumerate()
+                            .map(|(harmonic, amplitude)| {
+                                amplitude * (phase * (harmonic + 1) as f32).sin()
+                            })
+                            .sum()
+                    })
+                    .collect();
+
+                normalise(samples)
+            }
+            "FromFile" => {
+                // path, then the channel to read
+                expect_args(gen_name, args, 2)?;
+                let path = string_arg(gen_name, args, 0)?;
+                let channel = int_arg(gen_name, args, 1)?;
+
+                let file = SampleFile::load(path)?;
+                let samples = file.channel(channel.max(0) as usize);
+                if samples.is_empty() {
+                    return Err(format!("{path} has no channel {channel}"));
+                }
+
+                samples
+            }
+            "Segments" => {
+                // size, then a starting value followed by pairs of relative length and value to move to
+                expect_min_args(gen_name, args, 2)?;
+                if args.len() % 2 != 0 {
+                    return Err(format!(
+                        "{gen_name} needs a size then an odd number of values and lengths"
+                    ));
+                }
+
+                let size = size_arg(gen_name, args)?;
+                let points = (1..args.len())
+                    .map(|index| float_arg(gen_name, args, index))
+                    .collect::<Result<Vec<f32>, String>>()?;
+
+                let total_length: f32 = points.iter().skip(1).step_by(2).sum();
+                let mut samples = vec![points[0]; size];
+                if total_length > 0.0 {
+                    let mut start = 0.0;
+                    for segment in points.windows(3).step_by(2) {
+                        let (from, length, to) = (segment[0], segment[1], segment[2]);
+                        if length < 0.0 {
+                            return Err(format!("{gen_name} lengths can't be negative"));
+                        }
+
+                        let end = start + length / total_length * size as f32;
+                        // the first sample at or after the start, so a fractional start doesn't rewrite
+                        // the last sample of the previous segment
+                        for (index, sample) in
+                            samples.iter_mut().enumerate().skip(start.ceil() as usize)
+                        {
+                            if index as f32 >= end {
+                                break;
+                            }
+
+                            let fraction = (index as f32 - start) / (end - start);
+                            *sample = from + (to - from) * fraction;
+                        }
+
+                        start = end;
+                    }
+                }
+
+                samples
+            }
+            "Random" => {
+                // size and seed, so the same program always makes the same table
+                expect_args(gen_name, args, 2)?;
+                let size = size_arg(gen_name, args)?;
+                let mut rng = StdRng::seed_from_u64(int_arg(gen_name, args, 1)? as u64);
+                (0..size).map(|_| rng.gen_range(-1.0..=1.0)).collect()
+            }
+            "Window" => {
+                expect_args(gen_name, args, 2)?;
+                let size = size_arg(gen_name, args)?;
+                let shape = match WindowShape::try_from(int_arg(gen_name, args, 1)?) {
+                    Ok(shape) => shape,
+                    Err(_) => {
+                        return Err(format!("No window shape for integer {}", args[1].get_int()))
+                    }
+                };
+
+                let last = (size - 1).max(1) as f32;
+                (0..size)
+                    .map(|index| {
+                        let phase = 2.0 * PI * index as f32 / last;
+                        match shape {
+                            WindowShape::Hann => 0.5 - 0.5 * phase.cos(),
+                            WindowShape::Hamming => 0.54 - 0.46 * phase.cos(),
+                            WindowShape::Blackman => {
+                                0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos()
+                            }
+                            WindowShape::Triangle => 1.0 - (2.0 * index as f32 / last - 1.0).abs(),
+                        }
+                    })
+                    .collect()
+            }
+            _ => return Err(format!("No table generator named '{gen_name}'")),
+        };
+
+        Ok(Table { samples })
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Table({})", self.samples.len())
+    }
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Scales so the largest sample has a magnitude of 1.
+fn normalise(mut samples: Vec<f32>) -> Vec<f32> {
+    let peak = samples
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    if peak > 0.0 {
+        for sample in samples.iter_mut() {
+            *sample /= peak;
+        }
+    }
+
+    samples
+}
+
+fn expect_args(gen_name: &str, args: &[Value], count: usize) -> Result<(), String> {
+    if args.len() == count {
+        Ok(())
+    } else {
+        Err(format!(
+            "Expected {count} args to {gen_name} but got {}",
+            args.len()
+        ))
+    }
+}
+
+fn expect_min_args(gen_name: &str, args: &[Value], count: usize) -> Result<(), String> {
+    if args.len() >= count {
+        Ok(())
+    } else {
+        Err(format!(
+            "Expected at least {count} args to {gen_name} but got {}",
+            args.len()
+        ))
+    }
+}
+
+fn size_arg(gen_name: &str, args: &[Value]) -> Result<usize, String> {
+    match int_arg(gen_name, args, 0)? {
+        size if size > 0 => Ok(size as usize),
+        size => Err(format!(
+            "{gen_name} size must be greater than 0 but got {size}"
+        )),
+    }
+}
+
+fn int_arg(gen_name: &str, args: &[Value], index: usize) -> Result<i64, String> {
+    match args[index].value_type() {
+        ValueType::Int => Ok(args[index].get_int()),
+        value_type => Err(format!(
+            "Expected Int for arg at position {index} for {gen_name} but got {value_type:?}"
+        )),
+    }
+}
+
+fn float_arg(gen_name: &str, args: &[Value], index: usize) -> Result<f32, String> {
+    match args[index].value_type() {
+        ValueType::Int => Ok(args[index].get_int() as f32),
+        ValueType::Float => Ok(args[index].get_float()),
+        value_type => Err(format!(
+            "Expected Float for arg at position {index} for {gen_name} but got {value_type:?}"
+        )),
+    }
+}
+
+fn string_arg<'a>(gen_name: &str, args: &'a [Value], index: usize) -> Result<&'a String, String> {
+    match args[index].value_type() {
+        ValueType::String => Ok(args[index].get_string()),
+        value_type => Err(format!(
+            "Expected String for arg at position {index} for {gen_name} but got {value_type:?}"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(size: i64, points: &[f32]) -> Vec<f32> {
+        let mut args = vec![Value::int(size)];
+        args.extend(points.iter().map(|point| Value::float(*point)));
+        Table::generate("Segments", &args).unwrap().samples
+    }
+
+    #[test]
+    fn segments_with_fractional_breakpoint() {
+        // the first segment ends at 10 / 3, between samples 3 and 4
+        let samples = segments(10, &[0.0, 1.0, 1.0, 2.0, 0.0]);
+        let expected = [0.0, 0.3, 0.6, 0.9, 0.9, 0.75, 0.6, 0.45, 0.3, 0.15];
+        for (sample, expected) in samples.iter().zip(expected) {
+            assert!((sample - expected).abs() < 1e-5, "{samples:?}");
+        }
+    }
+}
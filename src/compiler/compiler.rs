@@ -1,14 +1,23 @@
-use std::{error::Error, fmt};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+};
 
 use colored::Colorize;
 
 use crate::{
-    audio::stream,
+    audio::{
+        components::component::ComponentType, resample::ResampleQuality, stream, table::Table,
+    },
     compiler::scanner::{Scanner, Token, TokenType},
     runtime::instrument::{Instrument, VariableType},
-    runtime::ops::Op,
+    runtime::ops::{LoopCounter, Op},
     runtime::vm::{self, VM},
-    runtime::{value::Value, vm::OutputTarget},
+    runtime::{
+        value::Value,
+        vm::{OutputTarget, Settings},
+    },
     utils::timer::Timer,
 };
 
@@ -39,9 +48,20 @@ enum CompilerContext {
     InstrumentsBlock,
     PerfFunc,
     ScoreBlock,
+    SettingsBlock,
+    TablesBlock,
     TopLevel,
 }
 
+/// Where a String given to a sample player came from, so the file can be loaded before the performance.
+#[derive(Clone)]
+enum PathSource {
+    Literal(String, Token),
+    // "init" or "perf" and the argument's index
+    Arg(&'static str, usize),
+    Unknown,
+}
+
 struct Compiler {
     file_path: String,
     scanner: Scanner,
@@ -49,6 +69,17 @@ struct Compiler {
     current: Option<Token>,
     had_error: bool,
     context_stack: Vec<CompilerContext>,
+    // number of locals being declared by the expression currently being compiled,
+    // so processors with multiple outputs can check they are assigned to the right number of locals
+    declaring_locals: Option<usize>,
+    // the loops around the code being compiled, outermost first, so components called in them get an instance per iteration.
+    // while loops and for loops without literal bounds are None, as the number of instances they need isn't known
+    loops: Vec<Option<LoopCounter>>,
+    // what has been assigned to the String members of the instrument being compiled
+    member_path_sources: HashMap<usize, Vec<PathSource>>,
+    // instrument name, "init" or "perf", and index of arguments used as sample paths,
+    // so the score's values for them can be loaded
+    sample_path_args: HashSet<(String, &'static str, usize)>,
     vm: VM,
 }
 
@@ -56,6 +87,7 @@ pub fn compile_and_run(
     code: String,
     file_path: String,
     output_target: OutputTarget,
+    settings: Settings,
 ) -> Result<(), Box<dyn Error>> {
     let mut compiler: Compiler = Compiler {
         file_path,
@@ -64,7 +96,11 @@ pub fn compile_and_run(
         previous: None,
         current: None,
         context_stack: Vec::<CompilerContext>::new(),
-        vm: VM::new(),
+        declaring_locals: None,
+        loops: Vec::new(),
+        member_path_sources: HashMap::new(),
+        sample_path_args: HashSet::new(),
+        vm: VM::new(settings),
     };
 
     {
@@ -87,20 +123,47 @@ impl Compiler {
     fn compile(&mut self) {
         self.context_stack.push(CompilerContext::TopLevel);
         self.advance();
+        let mut had_block = false;
+        let mut had_instruments = false;
         loop {
-            if self.match_token(TokenType::InstrumentsIdent) {
+            if self.match_token(TokenType::SettingsIdent) {
+                // instruments are checked against the settings, so they have to come first
+                if had_block {
+                    self.error_at_previous(
+                        "The settings block must come before any other blocks".to_string(),
+                    );
+                    break;
+                }
+
+                self.settings_block();
+            } else if self.match_token(TokenType::TablesIdent) {
+                // instruments refer to tables by name, so they have to exist first
+                if had_instruments {
+                    self.error_at_previous(
+                        "The tables block must come before the instruments and score".to_string(),
+                    );
+                    break;
+                }
+
+                self.tables_block();
+            } else if self.match_token(TokenType::InstrumentsIdent) {
                 self.instruments_block();
+                had_instruments = true;
             } else if self.match_token(TokenType::ScoreIdent) {
                 self.score_block();
+                had_instruments = true;
             } else if self.match_token(TokenType::EndOfFile) {
                 break;
             } else {
                 self.error_at_current(
-                    "Invalid token at top level; expected 'instruments' or 'score'".to_string(),
+                    "Invalid token at top level; expected 'settings', 'tables', 'instruments' or 'score'"
+                        .to_string(),
                 );
                 break;
             }
 
+            had_block = true;
+
             if self.had_error {
                 break;
             }
@@ -123,6 +186,63 @@ impl Compiler {
         }
     }
 
+    fn last_op(&self, instrument: &Instrument) -> Option<Op> {
+        match self.context_stack.last().unwrap() {
+            CompilerContext::InitFunc => instrument.last_init_op().cloned(),
+            CompilerContext::PerfFunc => instrument.last_perf_op().cloned(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn num_ops(&self, instrument: &Instrument) -> usize {
+        match self.context_stack.last().unwrap() {
+            CompilerContext::InitFunc => instrument.num_init_ops(),
+            CompilerContext::PerfFunc => instrument.num_perf_ops(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn patch_jump(&mut self, instrument: &mut Instrument, op_index: usize) {
+        match self.context_stack.last().unwrap() {
+            CompilerContext::InitFunc => {
+                instrument.patch_init_jump(op_index, instrument.num_init_ops())
+            }
+            CompilerContext::PerfFunc => {
+                instrument.patch_perf_jump(op_index, instrument.num_perf_ops())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn num_locals(&self, instrument: &Instrument) -> usize {
+        match self.context_stack.last().unwrap() {
+            CompilerContext::InitFunc => instrument.num_init_locals(),
+            CompilerContext::PerfFunc => instrument.num_perf_locals(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn add_local(
+        &mut self,
+        instrument: &mut Instrument,
+        name: String,
+        variable_type: VariableType,
+    ) -> bool {
+        match self.context_stack.last().unwrap() {
+            CompilerContext::InitFunc => instrument.add_init_local(name, variable_type),
+            CompilerContext::PerfFunc => instrument.add_perf_local(name, variable_type),
+            _ => unreachable!(),
+        }
+    }
+
+    fn end_scope(&mut self, instrument: &mut Instrument, first_local: usize) {
+        match self.context_stack.last().unwrap() {
+            CompilerContext::InitFunc => instrument.end_init_scope(first_local),
+            CompilerContext::PerfFunc => instrument.end_perf_scope(first_local),
+            _ => unreachable!(),
+        }
+    }
+
     fn advance(&mut self) {
         self.previous = self.current.clone();
         self.current = Some(self.scanner.scan_token());
@@ -149,13 +269,243 @@ impl Compiler {
         }
     }
 
+    fn settings_block(&mut self) {
+        self.context_stack.push(CompilerContext::SettingsBlock);
+        self.settings_block_contents();
+        // popped here so every error path in the block leaves the stack as it was
+        self.context_stack.pop();
+    }
+
+    fn settings_block_contents(&mut self) {
+        self.consume(TokenType::BraceOpen, "Expected '{'");
+
+        let mut file_settings = Settings::default();
+        loop {
+            if self.match_token(TokenType::BraceClose) {
+                break;
+            } else if self.match_token(TokenType::Identifier) {
+                self.setting(&mut file_settings);
+            } else {
+                self.error_at_current("Invalid token: expected setting name or '}'".to_string());
+            }
+
+            if self.had_error {
+                return;
+            }
+        }
+
+        if file_settings.block_size.is_some() && file_settings.control_rate.is_some() {
+            self.error_at_previous(
+                "Only one of 'blockSize' and 'controlRate' can be set".to_string(),
+            );
+            return;
+        }
+
+        // anything given on the command line takes priority
+        let settings = self.vm.settings_mut();
+        settings.sample_rate = settings.sample_rate.or(file_settings.sample_rate);
+        settings.channels = settings.channels.or(file_settings.channels);
+        settings.tail = settings.tail.or(file_settings.tail);
+        if settings.block_size.is_none() {
+            settings.block_size = file_settings.block_size;
+            settings.control_rate = file_settings.control_rate;
+        }
+    }
+
+    fn setting(&mut self, settings: &mut Settings) {
+        let name_token = self.previous.clone().unwrap();
+        let name = name_token.text().as_str();
+        if !matches!(
+            name,
+            "sampleRate" | "channels" | "blockSize" | "controlRate" | "tail"
+        ) {
+            self.error_at_previous(format!("Unknown setting '{name}', expected 'sampleRate', 'channels', 'blockSize', 'controlRate' or 'tail'"));
+            return;
+        }
+
+        self.consume(TokenType::Equal, "Expected '='");
+        if name == "tail" {
+            self.tail_setting(&name_token, settings);
+            return;
+        }
+
+        if !self.match_token(TokenType::Integer) {
+            self.error_at_current(format!("Expected Int for '{name}'"));
+            return;
+        }
+
+        let value = match self.previous.as_ref().unwrap().text().parse::<u32>() {
+            Ok(value) if value > 0 => value,
+            Ok(_) => {
+                self.error_at_previous(format!("'{name}' must be greater than 0"));
+                return;
+            }
+            Err(err) => {
+                self.error_at_previous(format!("Error parsing '{name}': {err}"));
+                return;
+            }
+        };
+
+        let already_set = match name {
+            "sampleRate" => settings.sample_rate.replace(value).is_some(),
+            "blockSize" => settings.block_size.replace(value).is_some(),
+            "controlRate" => settings.control_rate.replace(value).is_some(),
+            _ => match u16::try_from(value) {
+                Ok(channels) => settings.channels.replace(channels).is_some(),
+                Err(err) => {
+                    self.error_at_previous(format!("Error parsing '{name}': {err}"));
+                    return;
+                }
+            },
+        };
+
+        if already_set {
+            self.error(&name_token, format!("'{name}' is already set"));
+            return;
+        }
+
+        self.consume(TokenType::Semicolon, "Expected ';'");
+    }
+
+    /// The tail is in seconds so it can be an Int or Float, and 0 turns off the default tail for always on instruments.
+    fn tail_setting(&mut self, name_token: &Token, settings: &mut Settings) {
+        if !self.match_token(TokenType::Integer) && !self.match_token(TokenType::Float) {
+            self.error_at_current("Expected Int or Float for 'tail'".to_string());
+            return;
+        }
+
+        let value = match self.previous.as_ref().unwrap().text().parse::<f32>() {
+            Ok(value) => value,
+            Err(err) => {
+                self.error_at_previous(format!("Error parsing 'tail': {err}"));
+                return;
+            }
+        };
+
+        if settings.tail.replace(value).is_some() {
+            self.error(name_token, "'tail' is already set".to_string());
+            return;
+        }
+
+        self.consume(TokenType::Semicolon, "Expected ';'");
+    }
+
+    fn tables_block(&mut self) {
+        self.context_stack.push(CompilerContext::TablesBlock);
+        self.tables_block_contents();
+        self.context_stack.pop();
+    }
+
+    fn tables_block_contents(&mut self) {
+        self.consume(TokenType::BraceOpen, "Expected '{'");
+
+        loop {
+            if self.match_token(TokenType::BraceClose) {
+                break;
+            } else if self.match_token(TokenType::Identifier) {
+                self.table_declaration();
+            } else {
+                self.error_at_current("Invalid token: expected table name or '}'".to_string());
+            }
+
+            if self.had_error {
+                return;
+            }
+        }
+    }
+
+    fn table_declaration(&mut self) {
+        let table_name = self.previous.as_ref().unwrap().text().clone();
+        if table_name.chars().next().unwrap().is_uppercase() {
+            self.error_at_previous("Table names must not begin with a capital letter".to_string());
+            return;
+        }
+
+        if self.vm.table(&table_name).is_some() {
+            self.error_at_previous(format!("Duplicate table name '{table_name}'"));
+            return;
+        }
+
+        self.consume(TokenType::Equal, "Expected '='");
+        if !self.match_token(TokenType::Identifier) {
+            self.error_at_current("Expected table generator".to_string());
+            return;
+        }
+
+        let gen_token = self.previous.clone().unwrap();
+        self.consume(TokenType::ParenOpen, "Expected '('");
+
+        // generators run now, so their arguments must be literals
+        let mut args = Vec::<Value>::new();
+        loop {
+            if self.match_token(TokenType::ParenClose) {
+                break;
+            }
+
+            let negative = self.match_token(TokenType::Minus);
+            let value = if self.match_token(TokenType::Integer) {
+                self.previous
+                    .as_ref()
+                    .unwrap()
+                    .text()
+                    .parse::<i64>()
+                    .map(|value| Value::int(if negative { -value } else { value }))
+                    .map_err(|err| err.to_string())
+            } else if self.match_token(TokenType::Float) {
+                self.previous
+                    .as_ref()
+                    .unwrap()
+                    .text()
+                    .parse::<f32>()
+                    .map(|value| Value::float(if negative { -value } else { value }))
+                    .map_err(|err| err.to_string())
+            } else if !negative && self.match_token(TokenType::String) {
+                self.parse_string(self.previous.as_ref().unwrap().text())
+                    .map(Value::string)
+                    .map_err(|err| err.to_string())
+            } else {
+                self.error_at_current("Expected Int, Float or String literal".to_string());
+                return;
+            };
+
+            match value {
+                Ok(value) => args.push(value),
+                Err(err) => {
+                    self.error_at_previous(format!("Error parsing literal: {err}"));
+                    return;
+                }
+            }
+
+            if !self.check_token(TokenType::ParenClose) {
+                self.consume(TokenType::Comma, "Expected ','");
+            }
+
+            if self.had_error {
+                return;
+            }
+        }
+
+        match Table::generate(gen_token.text(), &args) {
+            Ok(table) => self.vm.add_table(table_name, table),
+            Err(err) => {
+                self.error(&gen_token, err);
+                return;
+            }
+        }
+
+        self.consume(TokenType::Semicolon, "Expected ';'");
+    }
+
     fn instruments_block(&mut self) {
         self.context_stack.push(CompilerContext::InstrumentsBlock);
         self.consume(TokenType::BraceOpen, "Expected '{'");
 
         loop {
             if self.match_token(TokenType::Identifier) {
-                self.instrument();
+                self.instrument(false);
+            } else if self.match_token(TokenType::Always) {
+                self.consume(TokenType::Identifier, "Expected instrument name");
+                self.instrument(true);
             } else if self.match_token(TokenType::BraceClose) {
                 break;
             } else {
@@ -171,9 +521,11 @@ impl Compiler {
         self.context_stack.pop();
     }
 
-    fn instrument(&mut self) {
+    fn instrument(&mut self, always_on: bool) {
         self.context_stack.push(CompilerContext::Instrument);
-        let mut instrument = Instrument::new(self.previous.as_ref().unwrap().text().clone());
+        self.member_path_sources.clear();
+        let mut instrument =
+            Instrument::new(self.previous.as_ref().unwrap().text().clone(), always_on);
         self.consume(TokenType::BraceOpen, "Expected '{'");
 
         loop {
@@ -233,6 +585,11 @@ impl Compiler {
         let context = *self.context_stack.last().unwrap();
 
         if self.match_token(TokenType::ParenOpen) {
+            if instrument.always_on() && !self.check_token(TokenType::ParenClose) {
+                self.error_at_current("Always on instruments can't take arguments".to_string());
+                return;
+            }
+
             loop {
                 if self.match_token(TokenType::Identifier) {
                     let arg_name_token = self.previous.as_ref().unwrap().clone();
@@ -244,7 +601,9 @@ impl Compiler {
                     self.consume(TokenType::Colon, "Expected ':'");
                     let type_token = self.current.as_ref().unwrap().token_type();
                     if type_token.is_type_ident() {
-                        if type_token == TokenType::AudioIdent {
+                        if type_token == TokenType::AudioIdent
+                            || type_token == TokenType::ControlIdent
+                        {
                             self.error_at_current("Invalid type for function argument".to_string());
                             return;
                         }
@@ -302,7 +661,12 @@ impl Compiler {
             }
         }
 
-        self.consume(TokenType::BraceOpen, "Expected '{");
+        self.block(instrument);
+    }
+
+    fn block(&mut self, instrument: &mut Instrument) {
+        self.consume(TokenType::BraceOpen, "Expected '{'");
+        let first_local = self.num_locals(instrument);
 
         loop {
             if self.match_token(TokenType::Local) {
@@ -317,6 +681,14 @@ impl Compiler {
                 break;
             }
         }
+
+        self.end_scope(instrument, first_local);
+    }
+
+    fn loop_body(&mut self, instrument: &mut Instrument, counter: Option<LoopCounter>) {
+        self.loops.push(counter);
+        self.block(instrument);
+        self.loops.pop();
     }
 
     fn local_declaration(&mut self, instrument: &mut Instrument) {
@@ -325,6 +697,7 @@ impl Compiler {
             return;
         }
 
+        let first_local = self.num_locals(instrument);
         let mut local_name_tokens = Vec::<Token>::new();
         while self.match_token(TokenType::Identifier) {
             let name_token = self.previous.as_ref().unwrap().clone();
@@ -376,20 +749,36 @@ impl Compiler {
 
         self.advance(); // consume type token
         self.consume(TokenType::Equal, "Expected '='");
-        
-        if let Some(expression_type) = self.expression(instrument) {
+
+        self.declaring_locals = Some(local_name_tokens.len());
+        let expression_type = self.expression(instrument);
+        self.declaring_locals = None;
+
+        if let Some(expression_type) = expression_type {
             if expression_type != type_token.to_variable_type() {
                 self.error_at_previous(format!("Type mismatch: expected '{:?}' for assignment to locals {:?} but got '{expression_type:?}'", type_token.to_variable_type(), local_name_tokens.iter().map(|token| token.text()).collect::<Vec<&String>>()));
                 return;
             }
 
-            self.emit_op(instrument, Op::DeclareLocal(local_name_tokens.len()));
+            self.emit_op(
+                instrument,
+                Op::DeclareLocal(first_local, local_name_tokens.len()),
+            );
             self.consume(TokenType::Semicolon, "Expected ';'");
         }
     }
 
     fn statement(&mut self, instrument: &mut Instrument) {
-        if self.match_token(TokenType::Print) {
+        if self.match_token(TokenType::If) {
+            self.if_statement(instrument);
+            return;
+        } else if self.match_token(TokenType::While) {
+            self.while_statement(instrument);
+            return;
+        } else if self.match_token(TokenType::For) {
+            self.for_statement(instrument);
+            return;
+        } else if self.match_token(TokenType::Print) {
             self.consume(TokenType::ParenOpen, "Expected '('");
             if self.match_token(TokenType::ParenClose) {
                 self.emit_op(instrument, Op::PrintEmpty);
@@ -409,26 +798,62 @@ impl Compiler {
             }
         } else if self.match_token(TokenType::Output) {
             self.consume(TokenType::ParenOpen, "Expected '('");
+            let num_channels = match self.audio_arguments(instrument, "output") {
+                Some(num_channels) => num_channels,
+                None => return,
+            };
 
-            while let Some(expression_type) = self.expression(instrument) {
-                if expression_type != VariableType::Audio {
+            // the command line's channel count if given, otherwise the settings block's
+            if let Some(channels) = self.vm.settings().channels {
+                if channels as usize != num_channels {
                     self.error_at_previous(format!(
-                        "Expected Audio for 'output' but got {expression_type:?}"
+                        "Expected {channels} channels for 'output' but got {num_channels}"
                     ));
                     return;
                 }
+            }
 
-                if self.match_token(TokenType::ParenClose) {
-                    break;
+            self.emit_op(instrument, Op::Output);
+        } else if self.match_token(TokenType::OutCh) {
+            self.consume(TokenType::ParenOpen, "Expected '('");
+            match self.expression(instrument) {
+                Some(VariableType::Int) => (),
+                Some(expression_type) => {
+                    self.error_at_previous(format!(
+                        "Expected Int for 'outch' channel but got {expression_type:?}"
+                    ));
+                    return;
                 }
+                None => return,
+            }
 
-                if !self.match_token(TokenType::Comma) {
-                    self.error_at_current("Expected ','".to_string());
+            self.consume(TokenType::Comma, "Expected ','");
+            match self.expression(instrument) {
+                Some(VariableType::Audio) => (),
+                Some(VariableType::Control) => self.emit_op(instrument, Op::Upsample),
+                Some(expression_type) => {
+                    self.error_at_previous(format!(
+                        "Expected Audio for 'outch' but got {expression_type:?}"
+                    ));
                     return;
                 }
+                None => return,
             }
 
-            self.emit_op(instrument, Op::Output);
+            self.consume(TokenType::ParenClose, "Expected ')'");
+            self.emit_op(instrument, Op::OutputChannel);
+        } else if self.match_token(TokenType::Send) {
+            self.consume(TokenType::ParenOpen, "Expected '('");
+            let bus_index = match self.bus_name() {
+                Some(bus_name) => self.vm.bus_index(&bus_name),
+                None => return,
+            };
+            self.consume(TokenType::Comma, "Expected ','");
+            if self.audio_arguments(instrument, "send").is_none() {
+                return;
+            }
+
+            self.emit_op(instrument, Op::Send(bus_index));
         } else if self.match_token(TokenType::Identifier) {
             self.assignment_statement(instrument);
         } else {
@@ -438,71 +863,341 @@ impl Compiler {
         self.consume(TokenType::Semicolon, "Expected ';'");
     }
 
-    fn assignment_statement(&mut self, instrument: &mut Instrument) {
-        let variable_name = self.previous.as_ref().unwrap().text().clone();
-        if let Some(index) = instrument.get_variable(&variable_name) {
-            self.consume(TokenType::Equal, "Expected '='");
-            let variable_type = instrument.member_type(index);
-            if let Some(expression_type) = self.expression(instrument) {
-                if variable_type != expression_type {
-                    self.error_at_previous(format!("Expected {variable_type:?} to assign to member variable '{variable_name}' but got {expression_type:?}"));
-                    return;
-                }
-                self.emit_op(instrument, Op::AssignMember(index));
+    fn condition(&mut self, instrument: &mut Instrument) -> bool {
+        if let Some(expression_type) = self.expression(instrument) {
+            if expression_type != VariableType::Bool {
+                self.error_at_previous(format!(
+                    "Expected Bool for condition but got {expression_type:?}"
+                ));
+                return false;
             }
+
+            true
         } else {
-            match self.context_stack.last().unwrap() {
-                CompilerContext::InitFunc => {
-                    if let Some(index) = instrument.get_local_init_variable(&variable_name) {
-                        self.consume(TokenType::Equal, "Expected '='");
-                        let variable_type = instrument.init_local_type(index);
-                        if let Some(expression_type) = self.expression(instrument) {
-                            if variable_type != expression_type {
-                                self.error_at_previous(format!("Expected {variable_type:?} to assign to local variable '{variable_name}' but got {expression_type:?}"));
-                                return;
-                            }
-                            self.emit_op(instrument, Op::AssignLocal(index));
-                        }
-                    } else {
-                        self.error_at_previous(format!(
-                            "No member variable named '{variable_name}'"
-                        ));
-                    }
-                }
-                CompilerContext::PerfFunc => {
-                    if let Some(index) = instrument.get_local_perf_variable(&variable_name) {
-                        self.consume(TokenType::Equal, "Expected '='");
-                        let variable_type = instrument.perf_local_type(index);
-                        if let Some(expression_type) = self.expression(instrument) {
-                            if variable_type != expression_type {
-                                self.error_at_previous(format!("Expected {variable_type:?} to assign to local variable '{variable_name}' but got {expression_type:?}"));
-                                return;
-                            }
-                            self.emit_op(instrument, Op::AssignLocal(index));
-                        }
-                    } else {
-                        self.error_at_previous(format!(
-                            "No member variable named '{variable_name}'"
-                        ));
-                    }
-                }
+            false
+        }
+    }
+
+    fn if_statement(&mut self, instrument: &mut Instrument) {
+        if !self.condition(instrument) {
+            return;
+        }
+
+        let jump_if_false = self.num_ops(instrument);
+        self.emit_op(instrument, Op::JumpIfFalse(0));
+        self.block(instrument);
+
+        if self.match_token(TokenType::Else) {
+            let jump_to_end = self.num_ops(instrument);
+            self.emit_op(instrument, Op::Jump(0));
+            self.patch_jump(instrument, jump_if_false);
+
+            if self.match_token(TokenType::If) {
+                self.if_statement(instrument);
+            } else {
+                self.block(instrument);
+            }
+
+            self.patch_jump(instrument, jump_to_end);
+        } else {
+            self.patch_jump(instrument, jump_if_false);
+        }
+    }
+
+    fn while_statement(&mut self, instrument: &mut Instrument) {
+        let loop_start = self.num_ops(instrument);
+        if !self.condition(instrument) {
+            return;
+        }
+
+        let jump_if_false = self.num_ops(instrument);
+        self.emit_op(instrument, Op::JumpIfFalse(0));
+        self.loop_body(instrument, None);
+        self.emit_op(instrument, Op::Jump(loop_start));
+        self.patch_jump(instrument, jump_if_false);
+    }
+
+    fn for_statement(&mut self, instrument: &mut Instrument) {
+        if !self.match_token(TokenType::Identifier) {
+            self.error_at_current("Expected identifier".to_string());
+            return;
+        }
+
+        let counter_token = self.previous.as_ref().unwrap().clone();
+        if counter_token.text().chars().next().unwrap().is_uppercase() {
+            self.error_at_previous(
+                "Argument and local identifier names must not begin with a capital letter"
+                    .to_string(),
+            );
+            return;
+        }
+
+        self.consume(TokenType::In, "Expected 'in'");
+
+        // the counter and the end of the range are only in scope for the loop,
+        // and are declared after both bounds so neither bound can refer to the counter
+        let first_local = self.num_locals(instrument);
+        let ops_before = self.num_ops(instrument);
+        if !self.range_bound(instrument, "start") {
+            return;
+        }
+
+        let start = self.literal(instrument, ops_before);
+        self.consume(TokenType::DotDot, "Expected '..'");
+
+        let ops_before = self.num_ops(instrument);
+        if !self.range_bound(instrument, "end") {
+            return;
+        }
+
+        let end = self.literal(instrument, ops_before);
+
+        if !self.add_local(instrument, counter_token.text().clone(), VariableType::Int) {
+            self.error(
+                &counter_token,
+                "A member variable, argument, or local variable with the same name already exists"
+                    .to_string(),
+            );
+            return;
+        }
+
+        // a space can never appear in an identifier so this can't clash with user locals
+        self.add_local(
+            instrument,
+            format!("{} end", counter_token.text()),
+            VariableType::Int,
+        );
+        self.emit_op(instrument, Op::DeclareLocal(first_local, 2));
+
+        let loop_start = self.num_ops(instrument);
+        let counter = match (start, end) {
+            (Some(start), Some(end)) => Some(LoopCounter {
+                local: first_local,
+                start: start.get_int(),
+                iterations: (end.get_int() - start.get_int()).max(0) as usize,
+            }),
+            _ => None,
+        };
+
+        self.emit_op(instrument, Op::RangeCheck(first_local, first_local + 1, 0));
+        self.loop_body(instrument, counter);
+        self.emit_op(instrument, Op::Increment(first_local));
+        self.emit_op(instrument, Op::Jump(loop_start));
+        self.patch_jump(instrument, loop_start);
+        self.end_scope(instrument, first_local);
+    }
+
+    /// The value of the expression compiled since `ops_before` if it was a single literal.
+    fn literal(&self, instrument: &Instrument, ops_before: usize) -> Option<Value> {
+        match self.last_op(instrument) {
+            Some(Op::LoadConstant(value)) if self.num_ops(instrument) == ops_before + 1 => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    fn range_bound(&mut self, instrument: &mut Instrument, bound: &str) -> bool {
+        if let Some(expression_type) = self.expression(instrument) {
+            if expression_type != VariableType::Int {
+                self.error_at_previous(format!(
+                    "Expected Int for {bound} of range but got {expression_type:?}"
+                ));
+                return false;
+            }
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn assignment_statement(&mut self, instrument: &mut Instrument) {
+        let variable_name = self.previous.as_ref().unwrap().text().clone();
+        if let Some(index) = instrument.get_variable(&variable_name) {
+            self.consume(TokenType::Equal, "Expected '='");
+            let variable_type = instrument.member_type(index);
+            let ops_before = self.num_ops(instrument);
+            if let Some(expression_type) = self.expression(instrument) {
+                if variable_type != expression_type {
+                    self.error_at_previous(format!("Expected {variable_type:?} to assign to member variable '{variable_name}' but got {expression_type:?}"));
+                    return;
+                }
+
+                if variable_type == VariableType::String {
+                    let sources = self.path_sources(instrument, ops_before);
+                    self.member_path_sources
+                        .entry(index)
+                        .or_default()
+                        .extend(sources);
+                }
+
+                self.emit_op(instrument, Op::AssignMember(index));
+            }
+        } else {
+            match self.context_stack.last().unwrap() {
+                CompilerContext::InitFunc => {
+                    if let Some(index) = instrument.get_local_init_variable(&variable_name) {
+                        self.consume(TokenType::Equal, "Expected '='");
+                        let variable_type = instrument.init_local_type(index);
+                        if let Some(expression_type) = self.expression(instrument) {
+                            if variable_type != expression_type {
+                                self.error_at_previous(format!("Expected {variable_type:?} to assign to local variable '{variable_name}' but got {expression_type:?}"));
+                                return;
+                            }
+                            self.emit_op(instrument, Op::AssignLocal(index));
+                        }
+                    } else {
+                        self.error_at_previous(format!(
+                            "No member variable named '{variable_name}'"
+                        ));
+                    }
+                }
+                CompilerContext::PerfFunc => {
+                    if let Some(index) = instrument.get_local_perf_variable(&variable_name) {
+                        self.consume(TokenType::Equal, "Expected '='");
+                        let variable_type = instrument.perf_local_type(index);
+                        if let Some(expression_type) = self.expression(instrument) {
+                            if variable_type != expression_type {
+                                self.error_at_previous(format!("Expected {variable_type:?} to assign to local variable '{variable_name}' but got {expression_type:?}"));
+                                return;
+                            }
+                            self.emit_op(instrument, Op::AssignLocal(index));
+                        }
+                    } else {
+                        self.error_at_previous(format!(
+                            "No member variable named '{variable_name}'"
+                        ));
+                    }
+                }
                 _ => unreachable!(),
             }
         }
     }
 
     fn expression(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
-        self.term(instrument)
+        self.logical_or(instrument)
+    }
+
+    #[must_use]
+    fn logical_or(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
+        if let Some(expression_type) = self.logical_and(instrument) {
+            loop {
+                if self.match_token(TokenType::Or) {
+                    if let Some(rhs_type) = self.logical_and(instrument) {
+                        if expression_type == VariableType::Bool && rhs_type == VariableType::Bool {
+                            self.emit_op(instrument, Op::Or);
+                        } else {
+                            self.error_at_previous(format!(
+                                "Cannot apply '||' to {expression_type:?} and {rhs_type:?}"
+                            ));
+                            return None;
+                        }
+                    }
+                } else {
+                    return Some(expression_type);
+                }
+            }
+        }
+
+        None
+    }
+
+    #[must_use]
+    fn logical_and(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
+        if let Some(expression_type) = self.equality(instrument) {
+            loop {
+                if self.match_token(TokenType::And) {
+                    if let Some(rhs_type) = self.equality(instrument) {
+                        if expression_type == VariableType::Bool && rhs_type == VariableType::Bool {
+                            self.emit_op(instrument, Op::And);
+                        } else {
+                            self.error_at_previous(format!(
+                                "Cannot apply '&&' to {expression_type:?} and {rhs_type:?}"
+                            ));
+                            return None;
+                        }
+                    }
+                } else {
+                    return Some(expression_type);
+                }
+            }
+        }
+
+        None
+    }
+
+    #[must_use]
+    fn equality(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
+        if let Some(mut expression_type) = self.comparison(instrument) {
+            loop {
+                let op = if self.match_token(TokenType::EqualEqual) {
+                    Op::Equal
+                } else if self.match_token(TokenType::BangEqual) {
+                    Op::NotEqual
+                } else {
+                    return Some(expression_type);
+                };
+
+                if let Some(rhs_type) = self.comparison(instrument) {
+                    if expression_type.can_equate_with(rhs_type) {
+                        self.emit_op(instrument, op);
+                        expression_type = VariableType::Bool;
+                    } else {
+                        self.error_at_previous(format!(
+                            "Cannot check equality of {expression_type:?} and {rhs_type:?}"
+                        ));
+                        return None;
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    #[must_use]
+    fn comparison(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
+        if let Some(mut expression_type) = self.term(instrument) {
+            loop {
+                let op = if self.match_token(TokenType::Less) {
+                    Op::Less
+                } else if self.match_token(TokenType::LessEqual) {
+                    Op::LessEqual
+                } else if self.match_token(TokenType::Greater) {
+                    Op::Greater
+                } else if self.match_token(TokenType::GreaterEqual) {
+                    Op::GreaterEqual
+                } else {
+                    return Some(expression_type);
+                };
+
+                if let Some(rhs_type) = self.term(instrument) {
+                    if expression_type.can_compare_with(rhs_type) {
+                        self.emit_op(instrument, op);
+                        expression_type = VariableType::Bool;
+                    } else {
+                        self.error_at_previous(format!(
+                            "Cannot compare {expression_type:?} with {rhs_type:?}"
+                        ));
+                        return None;
+                    }
+                }
+            }
+        }
+
+        None
     }
 
     #[must_use]
     fn term(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
-        if let Some(expression_type) = self.factor(instrument) {
+        if let Some(mut expression_type) = self.factor(instrument) {
             loop {
                 if self.match_token(TokenType::Minus) {
                     if let Some(rhs_type) = self.factor(instrument) {
                         if expression_type.can_sum_with(rhs_type) {
                             self.emit_op(instrument, Op::Subtract);
+                            expression_type = expression_type.arithmetic_result(rhs_type);
                         } else {
                             self.error_at_previous(format!(
                                 "Cannot subtract {rhs_type:?} from {expression_type:?}"
@@ -514,6 +1209,7 @@ impl Compiler {
                     if let Some(rhs_type) = self.factor(instrument) {
                         if expression_type.can_sum_with(rhs_type) {
                             self.emit_op(instrument, Op::Add);
+                            expression_type = expression_type.arithmetic_result(rhs_type);
                         } else {
                             self.error_at_previous(format!(
                                 "Cannot add {rhs_type:?} to {expression_type:?}"
@@ -532,12 +1228,13 @@ impl Compiler {
 
     #[must_use]
     fn factor(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
-        if let Some(expression_type) = self.call(instrument) {
+        if let Some(mut expression_type) = self.unary(instrument) {
             loop {
                 if self.match_token(TokenType::Slash) {
-                    if let Some(rhs_type) = self.call(instrument) {
+                    if let Some(rhs_type) = self.unary(instrument) {
                         if expression_type.can_factor_with(rhs_type) {
                             self.emit_op(instrument, Op::Divide);
+                            expression_type = expression_type.division_result(rhs_type);
                         } else {
                             self.error_at_previous(format!(
                                 "Cannot divide {expression_type:?} by {rhs_type:?}"
@@ -546,9 +1243,10 @@ impl Compiler {
                         }
                     }
                 } else if self.match_token(TokenType::Star) {
-                    if let Some(rhs_type) = self.call(instrument) {
+                    if let Some(rhs_type) = self.unary(instrument) {
                         if expression_type.can_factor_with(rhs_type) {
                             self.emit_op(instrument, Op::Multiply);
+                            expression_type = expression_type.arithmetic_result(rhs_type);
                         } else {
                             self.error_at_previous(format!(
                                 "Cannot multiply {expression_type:?} by {rhs_type:?}"
@@ -556,6 +1254,18 @@ impl Compiler {
                             return None;
                         }
                     }
+                } else if self.match_token(TokenType::Percent) {
+                    if let Some(rhs_type) = self.unary(instrument) {
+                        if expression_type.can_factor_with(rhs_type) {
+                            self.emit_op(instrument, Op::Modulo);
+                            expression_type = expression_type.division_result(rhs_type);
+                        } else {
+                            self.error_at_previous(format!(
+                                "Cannot take the remainder of {expression_type:?} divided by {rhs_type:?}"
+                            ));
+                            return None;
+                        }
+                    }
                 } else {
                     return Some(expression_type);
                 }
@@ -565,6 +1275,33 @@ impl Compiler {
         None
     }
 
+    #[must_use]
+    fn unary(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
+        if self.match_token(TokenType::Minus) {
+            self.declaring_locals = None;
+            let operand_type = self.unary(instrument)?;
+            if operand_type.can_negate() {
+                self.emit_op(instrument, Op::Negate);
+                Some(operand_type)
+            } else {
+                self.error_at_previous(format!("Cannot negate {operand_type:?}"));
+                None
+            }
+        } else if self.match_token(TokenType::Bang) {
+            self.declaring_locals = None;
+            let operand_type = self.unary(instrument)?;
+            if operand_type == VariableType::Bool {
+                self.emit_op(instrument, Op::Not);
+                Some(operand_type)
+            } else {
+                self.error_at_previous(format!("Cannot apply '!' to {operand_type:?}"));
+                None
+            }
+        } else {
+            self.call(instrument)
+        }
+    }
+
     #[must_use]
     fn call(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
         self.primary(instrument)
@@ -572,6 +1309,9 @@ impl Compiler {
 
     #[must_use]
     fn primary(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
+        // only the first primary of a local declaration's expression can assign to multiple locals
+        let declaring_locals = self.declaring_locals.take();
+
         if self.match_token(TokenType::Integer) {
             match self.previous.as_ref().unwrap().text().parse::<i64>() {
                 Ok(value) => {
@@ -594,6 +1334,12 @@ impl Compiler {
                     None
                 }
             }
+        } else if self.match_token(TokenType::True) {
+            self.emit_op(instrument, Op::LoadConstant(Value::bool(true)));
+            Some(VariableType::Bool)
+        } else if self.match_token(TokenType::False) {
+            self.emit_op(instrument, Op::LoadConstant(Value::bool(false)));
+            Some(VariableType::Bool)
         } else if self.match_token(TokenType::String) {
             match self.parse_string(self.previous.as_ref().unwrap().text()) {
                 Ok(value) => {
@@ -605,8 +1351,10 @@ impl Compiler {
                     None
                 }
             }
+        } else if self.match_token(TokenType::Receive) {
+            self.receive(instrument, declaring_locals)
         } else if self.match_token(TokenType::Identifier) {
-            self.identifier(instrument)
+            self.identifier(instrument, declaring_locals)
         } else if self.match_token(TokenType::ParenOpen) {
             let expression_type = self.expression(instrument);
             self.consume(TokenType::ParenClose, "Expected ')'");
@@ -617,6 +1365,93 @@ impl Compiler {
         }
     }
 
+    /// Compiles Audio arguments for `output` or `send` up to the closing ')', returning how many there were.
+    fn audio_arguments(
+        &mut self,
+        instrument: &mut Instrument,
+        statement_name: &str,
+    ) -> Option<usize> {
+        let mut num_channels = 0;
+        while let Some(expression_type) = self.expression(instrument) {
+            if expression_type == VariableType::Control {
+                self.emit_op(instrument, Op::Upsample);
+            } else if expression_type != VariableType::Audio {
+                self.error_at_previous(format!(
+                    "Expected Audio for '{statement_name}' but got {expression_type:?}"
+                ));
+                return None;
+            }
+
+            num_channels += 1;
+
+            if self.match_token(TokenType::ParenClose) {
+                break;
+            }
+
+            if !self.match_token(TokenType::Comma) {
+                self.error_at_current("Expected ','".to_string());
+                return None;
+            }
+        }
+
+        if self.had_error {
+            None
+        } else {
+            Some(num_channels)
+        }
+    }
+
+    fn receive(
+        &mut self,
+        instrument: &mut Instrument,
+        declaring_locals: Option<usize>,
+    ) -> Option<VariableType> {
+        self.consume(TokenType::ParenOpen, "Expected '('");
+        let bus_name = self.bus_name()?;
+        self.consume(TokenType::ParenClose, "Expected ')'");
+
+        // one Audio is received per channel, so like processors with multiple outputs it can't be used as an operand
+        let locals = match declaring_locals {
+            Some(locals) if self.check_token(TokenType::Semicolon) => locals,
+            _ => {
+                self.error_at_previous(
+                    "'receive' outputs one Audio per channel and must be assigned directly to locals"
+                        .to_string(),
+                );
+                return None;
+            }
+        };
+
+        if let Some(channels) = self.vm.settings().channels {
+            if channels as usize != locals {
+                self.error_at_previous(format!(
+                    "Bus '{bus_name}' has {channels} channels but 'receive' is assigned to {locals} locals"
+                ));
+                return None;
+            }
+        }
+
+        let bus_index = self.vm.bus_index(&bus_name);
+        self.emit_op(instrument, Op::Receive(bus_index, locals));
+        Some(VariableType::Audio)
+    }
+
+    /// Bus names must be String literals so they can be resolved to an index at compile time.
+    fn bus_name(&mut self) -> Option<String> {
+        if !self.match_token(TokenType::String) {
+            self.error_at_current("Expected String literal for bus name".to_string());
+            return None;
+        }
+
+        match self.parse_string(self.previous.as_ref().unwrap().text()) {
+            Ok(bus_name) => Some(bus_name),
+            Err(err) => {
+                self.error_at_previous(format!("Error parsing String: {err}"));
+                None
+            }
+        }
+    }
+
     fn is_escape_char(&self, c: char) -> Option<char> {
         match c {
             't' => Some('\t'),
@@ -657,7 +1492,11 @@ impl Compiler {
         Ok(res)
     }
 
-    fn identifier(&mut self, instrument: &mut Instrument) -> Option<VariableType> {
+    fn identifier(
+        &mut self,
+        instrument: &mut Instrument,
+        declaring_locals: Option<usize>,
+    ) -> Option<VariableType> {
         let ident_text = self.previous.as_ref().unwrap().text().clone();
         if ident_text.chars().next().unwrap().is_uppercase() {
             if !vm::has_component(&ident_text) {
@@ -665,26 +1504,91 @@ impl Compiler {
                 return None;
             }
 
+            // each iteration gets its own instance, so the number of iterations must be known.
+            // the call is still compiled so parsing carries on from the end of it
+            let loop_counters = self
+                .loops
+                .iter()
+                .copied()
+                .collect::<Option<Vec<LoopCounter>>>();
+            if loop_counters.is_none() {
+                self.error_at_previous(format!(
+                    "'{ident_text}' can only be used in for loops with literal bounds, not while loops or for loops with variable bounds"
+                ));
+            }
+
             let info = vm::component_info(&ident_text);
             self.consume(TokenType::ParenOpen, "Expected '('");
 
             let mut arg_count = 0;
+            let mut constants = Vec::<(usize, Value, Token)>::new();
             loop {
                 if self.match_token(TokenType::ParenClose) {
                     break;
                 } else {
-                    if arg_count == info.input_types.len() {
+                    if arg_count == info.input_types.len() && info.repeated_inputs == 0 {
                         self.error_at_current(format!("Too many inputs to '{ident_text}'"));
                         return None;
                     }
 
+                    let ops_before = self.num_ops(instrument);
                     if let Some(expression_type) = self.expression(instrument) {
-                        if expression_type != info.input_types[arg_count] {
-                            self.error_at_previous(format!("Expected {:?} for input at position {arg_count} 
-                            for {ident_text} but got {expression_type:?}", info.input_types[arg_count]));
+                        if let ComponentType::Processor { audio_inputs, .. } = info.component_type {
+                            if arg_count < audio_inputs
+                                && !matches!(
+                                    expression_type,
+                                    VariableType::Audio | VariableType::Control
+                                )
+                            {
+                                self.error_at_previous(format!("Input at position {arg_count} for {ident_text} is one of its {audio_inputs} audio inputs, expected Audio but got {expression_type:?}"));
+                                return None;
+                            }
+                        }
+
+                        let input_types = info.input_type(arg_count);
+                        if expression_type == VariableType::Control
+                            && input_types.contains(&VariableType::Audio)
+                        {
+                            // interpolated across the block so stepped control values don't zipper
+                            self.emit_op(instrument, Op::Upsample);
+                        } else if !input_types.contains(&expression_type) {
+                            let expected = input_types
+                                .iter()
+                                .map(|input_type| format!("{input_type:?}"))
+                                .collect::<Vec<String>>()
+                                .join(" or ");
+                            self.error_at_previous(format!("Expected {expected} for input at position {arg_count} for {ident_text} but got {expression_type:?}"));
                             return None;
                         }
 
+                        if info.constant_inputs.contains(&arg_count) {
+                            match self.literal(instrument, ops_before) {
+                                Some(value) => {
+                                    constants.push((
+                                        arg_count,
+                                        value,
+                                        self.previous.clone().unwrap(),
+                                    ));
+                                }
+                                None => {
+                                    self.error_at_previous(format!("Input at position {arg_count} for {ident_text} must be a literal"));
+                                    return None;
+                                }
+                            }
+                        }
+
+                        if let Some((path_input, quality_input)) = info.sample_inputs {
+                            if arg_count == path_input
+                                && !self.sample_path(instrument, &ident_text, ops_before)
+                            {
+                                return None;
+                            } else if arg_count == quality_input
+                                && !self.resample_quality(instrument, ops_before)
+                            {
+                                return None;
+                            }
+                        }
+
                         arg_count += 1;
 
                         if !self.check_token(TokenType::ParenClose) {
@@ -696,22 +1600,87 @@ impl Compiler {
                 }
             }
 
-            if arg_count != info.input_types.len() {
+            if !info.accepts_arg_count(arg_count) {
+                let expected = if info.optional_inputs > 0 {
+                    format!(
+                        "{} to {} input args",
+                        info.input_types.len() - info.optional_inputs,
+                        info.input_types.len()
+                    )
+                } else if info.repeated_inputs == 0 {
+                    format!("{} input args", info.input_types.len())
+                } else {
+                    format!(
+                        "{} input args followed by groups of {}",
+                        info.input_types.len() - info.repeated_inputs,
+                        info.repeated_inputs
+                    )
+                };
                 self.error_at_previous(format!(
-                    "Expected {} input args to {ident_text} but got {arg_count}",
-                    info.input_types.len()
+                    "Expected {expected} to {ident_text} but got {arg_count}"
                 ));
                 return None;
             }
 
-            let index = match self.context_stack.last().unwrap() {
-                CompilerContext::InitFunc => instrument.add_init_component((info.factory)()),
-                CompilerContext::PerfFunc => instrument.add_perf_component((info.factory)()),
+            // a left out resample quality is linear
+            if let Some((_, quality_input)) = info.sample_inputs {
+                if arg_count <= quality_input {
+                    self.vm.add_resample_quality(ResampleQuality::Linear);
+                }
+            }
+
+            let mut component = (info.factory)();
+            for (position, value, token) in constants {
+                if let Err(err) = component.constant_input(position, &value) {
+                    self.error(&token, err);
+                    return None;
+                }
+            }
+
+            let loop_counters = loop_counters.unwrap_or_default();
+            let instances = loop_counters
+                .iter()
+                .map(|counter| counter.iterations)
+                .product::<usize>()
+                .max(1);
+
+            let context = *self.context_stack.last().unwrap();
+            let mut add_component = |component| match context {
+                CompilerContext::InitFunc => instrument.add_init_component(component),
+                CompilerContext::PerfFunc => instrument.add_perf_component(component),
                 _ => unreachable!(),
             };
 
-            self.emit_op(instrument, Op::CallComponent(index));
+            // the instances are added together so the loop counters can index from the first
+            let index = add_component(component.clone());
+            for _ in 1..instances {
+                add_component(component.clone());
+            }
+
+            // processors with multiple outputs leave them all on the stack,
+            // so they can't be used as an operand and must be assigned directly to one local per output
+            if let ComponentType::Processor { audio_outputs, .. } = info.component_type {
+                if audio_outputs > 1
+                    && (declaring_locals != Some(audio_outputs)
+                        || !self.check_token(TokenType::Semicolon))
+                {
+                    self.error_at_previous(format!("{ident_text} outputs {audio_outputs} channels and must be assigned directly to {audio_outputs} locals"));
+                    return None;
+                }
+            }
+
+            if loop_counters.is_empty() {
+                self.emit_op(instrument, Op::CallComponent(index, arg_count));
+            } else {
+                self.emit_op(
+                    instrument,
+                    Op::CallLoopComponent(index, arg_count, loop_counters),
+                );
+            }
+
             Some(info.output_type)
+        } else if vm::has_function(&ident_text) && self.check_token(TokenType::ParenOpen) {
+            self.function_call(instrument, ident_text)
         } else {
             match self.context_stack.last().unwrap() {
                 CompilerContext::InitFunc => {
@@ -724,9 +1693,12 @@ impl Compiler {
                     } else if let Some(index) = instrument.get_variable(&ident_text) {
                         self.emit_op(instrument, Op::LoadMember(index));
                         Some(instrument.member_type(index))
+                    } else if let Some(table) = self.vm.table(&ident_text) {
+                        self.emit_op(instrument, Op::LoadConstant(Value::table(table)));
+                        Some(VariableType::Table)
                     } else {
                         self.error_at_previous(format!(
-                            "No member variable, argument, or local variable found named '{ident_text}'"
+                            "No member variable, argument, local variable, or table found named '{ident_text}'"
                         ));
                         None
                     }
@@ -741,9 +1713,12 @@ impl Compiler {
                     } else if let Some(index) = instrument.get_variable(&ident_text) {
                         self.emit_op(instrument, Op::LoadMember(index));
                         Some(instrument.member_type(index))
+                    } else if let Some(table) = self.vm.table(&ident_text) {
+                        self.emit_op(instrument, Op::LoadConstant(Value::table(table)));
+                        Some(VariableType::Table)
                     } else {
                         self.error_at_previous(format!(
-                            "No member variable, argument, or local variable found named '{ident_text}'"
+                            "No member variable, argument, local variable, or table found named '{ident_text}'"
                         ));
                         None
                     }
@@ -753,6 +1728,132 @@ impl Compiler {
         }
     }
 
+    /// Finds where the String compiled since `ops_before` came from.
+    fn path_sources(&self, instrument: &Instrument, ops_before: usize) -> Vec<PathSource> {
+        if self.num_ops(instrument) != ops_before + 1 {
+            return vec![PathSource::Unknown];
+        }
+
+        match self.last_op(instrument) {
+            Some(Op::LoadConstant(value)) => vec![PathSource::Literal(
+                value.get_string().clone(),
+                self.previous.clone().unwrap(),
+            )],
+            Some(Op::LoadArg(index)) => match self.context_stack.last().unwrap() {
+                CompilerContext::InitFunc => vec![PathSource::Arg("init", index)],
+                CompilerContext::PerfFunc => vec![PathSource::Arg("perf", index)],
+                _ => unreachable!(),
+            },
+            Some(Op::LoadMember(index)) => self
+                .member_path_sources
+                .get(&index)
+                .cloned()
+                .unwrap_or_else(|| vec![PathSource::Unknown]),
+            _ => vec![PathSource::Unknown],
+        }
+    }
+
+    /// Loads the files a sample player's path can refer to, or marks the arguments it comes from
+    /// so the score's values for them are loaded.
+    fn sample_path(
+        &mut self,
+        instrument: &Instrument,
+        component_name: &String,
+        ops_before: usize,
+    ) -> bool {
+        for source in self.path_sources(instrument, ops_before) {
+            match source {
+                PathSource::Literal(path, token) => {
+                    if let Err(err) = self.vm.load_sample(&path) {
+                        self.error(&token, err);
+                        return false;
+                    }
+                }
+                PathSource::Arg(func, index) => {
+                    self.sample_path_args
+                        .insert((instrument.name().clone(), func, index));
+                }
+                PathSource::Unknown => {
+                    self.error_at_previous(format!("The path given to {component_name} must be a String literal, an argument, or a member variable assigned one, so the file can be loaded before the performance"));
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn resample_quality(&mut self, instrument: &Instrument, ops_before: usize) -> bool {
+        match self.last_op(instrument) {
+            Some(Op::LoadConstant(value)) if self.num_ops(instrument) == ops_before + 1 => {
+                match ResampleQuality::try_from(value.get_int()) {
+                    Ok(quality) => self.vm.add_resample_quality(quality),
+                    Err(_) => {
+                        self.error_at_previous(format!(
+                            "No resample quality for integer {}",
+                            value.get_int()
+                        ));
+                        return false;
+                    }
+                }
+            }
+            _ => {
+                // not known until the performance, so have every quality ready
+                self.vm.add_resample_quality(ResampleQuality::Linear);
+                self.vm.add_resample_quality(ResampleQuality::Cubic);
+                self.vm.add_resample_quality(ResampleQuality::Sinc);
+            }
+        }
+
+        true
+    }
+
+    fn function_call(
+        &mut self,
+        instrument: &mut Instrument,
+        function_name: String,
+    ) -> Option<VariableType> {
+        let info = vm::function_info(&function_name);
+        self.consume(TokenType::ParenOpen, "Expected '('");
+
+        let mut arg_types = Vec::<VariableType>::new();
+        loop {
+            if self.match_token(TokenType::ParenClose) {
+                break;
+            }
+
+            if arg_types.len() == info.arg_count {
+                self.error_at_current(format!("Too many arguments to '{function_name}'"));
+                return None;
+            }
+
+            arg_types.push(self.expression(instrument)?);
+
+            if !self.check_token(TokenType::ParenClose) {
+                self.consume(TokenType::Comma, "Expected ','");
+            }
+        }
+
+        if arg_types.len() != info.arg_count {
+            self.error_at_previous(format!(
+                "Expected {} arguments to {function_name} but got {}",
+                info.arg_count,
+                arg_types.len()
+            ));
+            return None;
+        }
+
+        if let Some(return_type) = (info.return_type)(&arg_types) {
+            self.emit_op(instrument, Op::CallFunction(info));
+            Some(return_type)
+        } else {
+            self.error_at_previous(format!(
+                "Invalid argument types {arg_types:?} for {function_name}"
+            ));
+            None
+        }
+    }
+
     fn score_block(&mut self) {
         self.context_stack.push(CompilerContext::ScoreBlock);
         self.consume(TokenType::BraceOpen, "Expected '{'");
@@ -781,6 +1882,13 @@ impl Compiler {
             return;
         }
 
+        if self.vm.instrument_always_on(&instrument_name) {
+            self.error_at_previous(format!(
+                "'{instrument_name}' is always on and can't be used in the score"
+            ));
+            return;
+        }
+
         self.consume(TokenType::ParenOpen, "Expected '('");
 
         if !self.match_token(TokenType::Float) {
@@ -832,59 +1940,15 @@ impl Compiler {
                         return;
                     }
 
-                    match self
+                    let arg_type = self
                         .vm
-                        .instrument_init_arg_type(&instrument_name, arg_count)
+                        .instrument_init_arg_type(&instrument_name, arg_count);
+                    if let Some(value) =
+                        self.score_arg(&instrument_name, arg_type, arg_count, "init")
                     {
-                        VariableType::Float => {
-                            if !self.match_token(TokenType::Float) {
-                                self.error_at_current(format!(
-                                    "Expected Float for init arg at position {arg_count}"
-                                ));
-                                return;
-                            }
-
-                            match self.previous.as_ref().unwrap().text().parse::<f32>() {
-                                Ok(value) => init_args.push(Value::float(value)),
-                                Err(err) => {
-                                    self.error_at_previous(format!("Error parsing Float: {err}"));
-                                    return;
-                                }
-                            }
-                        }
-                        VariableType::Int => {
-                            if !self.match_token(TokenType::Integer) {
-                                self.error_at_current(format!(
-                                    "Expected Int for init arg at position {arg_count}"
-                                ));
-                                return;
-                            }
-
-                            match self.previous.as_ref().unwrap().text().parse::<i64>() {
-                                Ok(value) => init_args.push(Value::int(value)),
-                                Err(err) => {
-                                    self.error_at_previous(format!("Error parsing Int: {err}"));
-                                    return;
-                                }
-                            }
-                        }
-                        VariableType::String => {
-                            if !self.match_token(TokenType::String) {
-                                self.error_at_current(format!(
-                                    "Expected String for init arg at position {arg_count}"
-                                ));
-                                return;
-                            }
-
-                            match self.parse_string(self.previous.as_ref().unwrap().text()) {
-                                Ok(value) => init_args.push(Value::string(value)),
-                                Err(err) => {
-                                    self.error_at_previous(format!("Error parsing String: {err}"));
-                                    return;
-                                }
-                            }
-                        }
-                        _ => unreachable!(),
+                        init_args.push(value);
+                    } else {
+                        return;
                     }
 
                     arg_count += 1;
@@ -911,59 +1975,15 @@ impl Compiler {
                         return;
                     }
 
-                    match self
+                    let arg_type = self
                         .vm
-                        .instrument_perf_arg_type(&instrument_name, arg_count)
+                        .instrument_perf_arg_type(&instrument_name, arg_count);
+                    if let Some(value) =
+                        self.score_arg(&instrument_name, arg_type, arg_count, "perf")
                     {
-                        VariableType::Float => {
-                            if !self.match_token(TokenType::Float) {
-                                self.error_at_current(format!(
-                                    "Expected Float for perf arg at position {arg_count}"
-                                ));
-                                return;
-                            }
-
-                            match self.previous.as_ref().unwrap().text().parse::<f32>() {
-                                Ok(value) => perf_args.push(Value::float(value)),
-                                Err(err) => {
-                                    self.error_at_previous(format!("Error parsing Float: {err}"));
-                                    return;
-                                }
-                            }
-                        }
-                        VariableType::Int => {
-                            if !self.match_token(TokenType::Integer) {
-                                self.error_at_current(format!(
-                                    "Expected Int for perf arg at position {arg_count}"
-                                ));
-                                return;
-                            }
-
-                            match self.previous.as_ref().unwrap().text().parse::<i64>() {
-                                Ok(value) => perf_args.push(Value::int(value)),
-                                Err(err) => {
-                                    self.error_at_previous(format!("Error parsing Int: {err}"));
-                                    return;
-                                }
-                            }
-                        }
-                        VariableType::String => {
-                            if !self.match_token(TokenType::String) {
-                                self.error_at_current(format!(
-                                    "Expected String for perf arg at position {arg_count}"
-                                ));
-                                return;
-                            }
-
-                            match self.parse_string(self.previous.as_ref().unwrap().text()) {
-                                Ok(value) => perf_args.push(Value::string(value)),
-                                Err(err) => {
-                                    self.error_at_previous(format!("Error parsing String: {err}"));
-                                    return;
-                                }
-                            }
-                        }
-                        _ => unreachable!(),
+                        perf_args.push(value);
+                    } else {
+                        return;
                     }
 
                     arg_count += 1;
@@ -998,6 +2018,110 @@ impl Compiler {
         self.consume(TokenType::Semicolon, "Expected ';'");
     }
 
+    fn score_arg(
+        &mut self,
+        instrument_name: &String,
+        arg_type: VariableType,
+        arg_count: usize,
+        func: &'static str,
+    ) -> Option<Value> {
+        match arg_type {
+            VariableType::Float => {
+                let negative = self.match_token(TokenType::Minus);
+                if !self.match_token(TokenType::Float) {
+                    self.error_at_current(format!(
+                        "Expected Float for {func} arg at position {arg_count}"
+                    ));
+                    return None;
+                }
+
+                match self.previous.as_ref().unwrap().text().parse::<f32>() {
+                    Ok(value) => Some(Value::float(if negative { -value } else { value })),
+                    Err(err) => {
+                        self.error_at_previous(format!("Error parsing Float: {err}"));
+                        None
+                    }
+                }
+            }
+            VariableType::Int => {
+                let negative = self.match_token(TokenType::Minus);
+                if !self.match_token(TokenType::Integer) {
+                    self.error_at_current(format!(
+                        "Expected Int for {func} arg at position {arg_count}"
+                    ));
+                    return None;
+                }
+
+                match self.previous.as_ref().unwrap().text().parse::<i64>() {
+                    Ok(value) => Some(Value::int(if negative { -value } else { value })),
+                    Err(err) => {
+                        self.error_at_previous(format!("Error parsing Int: {err}"));
+                        None
+                    }
+                }
+            }
+            VariableType::Bool => {
+                if self.match_token(TokenType::True) {
+                    Some(Value::bool(true))
+                } else if self.match_token(TokenType::False) {
+                    Some(Value::bool(false))
+                } else {
+                    self.error_at_current(format!(
+                        "Expected Bool for {func} arg at position {arg_count}"
+                    ));
+                    None
+                }
+            }
+            VariableType::String => {
+                if !self.match_token(TokenType::String) {
+                    self.error_at_current(format!(
+                        "Expected String for {func} arg at position {arg_count}"
+                    ));
+                    return None;
+                }
+
+                match self.parse_string(self.previous.as_ref().unwrap().text()) {
+                    Ok(value) => {
+                        if self.sample_path_args.contains(&(
+                            instrument_name.clone(),
+                            func,
+                            arg_count,
+                        )) {
+                            if let Err(err) = self.vm.load_sample(&value) {
+                                self.error_at_previous(err);
+                                return None;
+                            }
+                        }
+
+                        Some(Value::string(value))
+                    }
+                    Err(err) => {
+                        self.error_at_previous(format!("Error parsing String: {err}"));
+                        None
+                    }
+                }
+            }
+            VariableType::Table => {
+                if !self.match_token(TokenType::Identifier) {
+                    self.error_at_current(format!(
+                        "Expected table name for {func} arg at position {arg_count}"
+                    ));
+                    return None;
+                }
+
+                let table_name = self.previous.as_ref().unwrap().text().clone();
+                match self.vm.table(&table_name) {
+                    Some(table) => Some(Value::table(table)),
+                    None => {
+                        self.error_at_previous(format!("No table named '{table_name}'"));
+                        None
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn had_error(&self) -> bool {
         self.had_error
     }
@@ -6,16 +6,32 @@ use crate::runtime::instrument::VariableType;
 static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "instruments" => TokenType::InstrumentsIdent,
     "score" => TokenType::ScoreIdent,
+    "settings" => TokenType::SettingsIdent,
     "Int" => TokenType::IntIdent,
     "Float" => TokenType::FloatIdent,
     "Audio" => TokenType::AudioIdent,
+    "Control" => TokenType::ControlIdent,
     "String" => TokenType::StringIdent,
+    "Bool" => TokenType::BoolIdent,
+    "Table" => TokenType::TableIdent,
+    "tables" => TokenType::TablesIdent,
+    "true" => TokenType::True,
+    "false" => TokenType::False,
     "init" => TokenType::InitIdent,
     "perf" => TokenType::PerfIdent,
     "print" => TokenType::Print,
     "println" => TokenType::PrintLn,
     "local" => TokenType::Local,
     "output" => TokenType::Output,
+    "outch" => TokenType::OutCh,
+    "send" => TokenType::Send,
+    "receive" => TokenType::Receive,
+    "always" => TokenType::Always,
+    "if" => TokenType::If,
+    "else" => TokenType::Else,
+    "while" => TokenType::While,
+    "for" => TokenType::For,
+    "in" => TokenType::In,
 };
 
 static SYMBOLS: phf::Map<&'static str, TokenType> = phf_map! {
@@ -31,6 +47,17 @@ static SYMBOLS: phf::Map<&'static str, TokenType> = phf_map! {
     "+" => TokenType::Plus,
     "*" => TokenType::Star,
     "/" => TokenType::Slash,
+    "%" => TokenType::Percent,
+    ".." => TokenType::DotDot,
+    "!" => TokenType::Bang,
+    "!=" => TokenType::BangEqual,
+    "==" => TokenType::EqualEqual,
+    "<" => TokenType::Less,
+    "<=" => TokenType::LessEqual,
+    ">" => TokenType::Greater,
+    ">=" => TokenType::GreaterEqual,
+    "&&" => TokenType::And,
+    "||" => TokenType::Or,
 };
 
 pub struct Scanner {
@@ -43,36 +70,63 @@ pub struct Scanner {
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TokenType {
+    Always,
+    And,
     AudioIdent,
+    Bang,
+    BangEqual,
+    BoolIdent,
     BraceOpen,
     BraceClose,
     Colon,
     Comma,
+    ControlIdent,
+    DotDot,
+    Else,
     EndOfFile,
     Equal,
+    EqualEqual,
     ErrorToken,
+    False,
     Float,
     FloatIdent,
+    For,
+    Greater,
+    GreaterEqual,
     Identifier,
+    If,
+    In,
     InitIdent,
     InstrumentsIdent,
     IntIdent,
     Integer,
+    Less,
+    LessEqual,
     Local,
     Minus,
+    Or,
+    OutCh,
     Output,
     ParenOpen,
     ParenClose,
+    Percent,
     PerfIdent,
     Plus,
     Print,
     PrintLn,
+    Receive,
     ScoreIdent,
     Semicolon,
+    Send,
+    SettingsIdent,
     Slash,
     Star,
     String,
     StringIdent,
+    TableIdent,
+    TablesIdent,
+    True,
+    While,
 }
 
 pub struct Token {
@@ -140,7 +194,10 @@ impl TokenType {
         self == TokenType::FloatIdent
             || self == TokenType::IntIdent
             || self == TokenType::AudioIdent
+            || self == TokenType::ControlIdent
             || self == TokenType::StringIdent
+            || self == TokenType::BoolIdent
+            || self == TokenType::TableIdent
     }
 
     pub fn to_variable_type(self) -> VariableType {
@@ -149,12 +206,19 @@ impl TokenType {
             TokenType::FloatIdent => VariableType::Float,
             TokenType::StringIdent => VariableType::String,
             TokenType::AudioIdent => VariableType::Audio,
+            TokenType::ControlIdent => VariableType::Control,
+            TokenType::BoolIdent => VariableType::Bool,
+            TokenType::TableIdent => VariableType::Table,
             _ => panic!("Cannot convert {self:?} to VariableType"),
         }
     }
 
     pub fn is_literal(self) -> bool {
-        self == TokenType::Integer || self == TokenType::Float || self == TokenType::String
+        self == TokenType::Integer
+            || self == TokenType::Float
+            || self == TokenType::String
+            || self == TokenType::True
+            || self == TokenType::False
     }
 
     pub fn is_operator(self) -> bool {
@@ -208,7 +272,7 @@ impl Scanner {
                 count += 1;
             }
         }
-        
+
         count
     }
 
@@ -225,7 +289,15 @@ impl Scanner {
                 return self.number();
             }
 
-            if let Some(token_type) = SYMBOLS.get(&self.code[self.start..self.current]) {
+            // two character symbols take priority over their single character prefixes
+            if let Some(token_type) = self
+                .code
+                .get(self.start..self.current + 1)
+                .and_then(|symbol| SYMBOLS.get(symbol))
+            {
+                self.advance();
+                self.make_token(*token_type)
+            } else if let Some(token_type) = SYMBOLS.get(&self.code[self.start..self.current]) {
                 self.make_token(*token_type)
             } else if current == '"' {
                 self.string()
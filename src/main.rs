@@ -1,8 +1,14 @@
 #![feature(once_cell)]
 
-use std::{error::Error, fmt, path::Path, fs};
+use std::{
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use runtime::vm::OutputTarget;
+use audio::file_format::{FileFormat, SampleFormat};
+use runtime::vm::{OutputTarget, Settings};
 
 mod audio;
 mod compiler;
@@ -22,6 +28,15 @@ impl Error for ArgumentError {}
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = std::env::args().collect();
+    if args
+        .iter()
+        .skip(1)
+        .any(|arg| arg == "-h" || arg == "--help")
+    {
+        usage();
+        return Ok(());
+    }
+
     if args.len() < 2 {
         usage();
         return Err(Box::new(ArgumentError(String::from(
@@ -30,8 +45,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     let mut output_target = OutputTarget::None;
+    let mut settings = Settings::default();
     let file_path = Path::new(&args[1]);
-    for arg in args.iter().skip(2) {
+    let mut args_iter = args.iter().skip(2);
+    while let Some(arg) = args_iter.next() {
         if arg == "--dac" {
             if output_target != OutputTarget::None {
                 usage();
@@ -48,21 +65,107 @@ fn main() -> Result<(), Box<dyn Error>> {
                 ))));
             }
             output_target = OutputTarget::File;
+        } else if arg == "-o" || arg == "--output" {
+            settings.output_path = Some(PathBuf::from(option_value(arg, args_iter.next())?));
+        } else if arg == "-f" || arg == "--format" {
+            settings.file_format = Some(
+                option_value(arg, args_iter.next())?
+                    .parse::<FileFormat>()
+                    .map_err(|err| ArgumentError(err.to_string()))?,
+            );
+        } else if arg == "-s" || arg == "--sample-format" {
+            settings.sample_format = Some(
+                option_value(arg, args_iter.next())?
+                    .parse::<SampleFormat>()
+                    .map_err(|err| ArgumentError(err.to_string()))?,
+            );
+        } else if arg == "-r" || arg == "--sample-rate" {
+            settings.sample_rate = Some(parse_option(arg, args_iter.next())?);
+        } else if arg == "-c" || arg == "--channels" {
+            settings.channels = Some(parse_option(arg, args_iter.next())?);
+        } else if arg == "-b" || arg == "--block-size" {
+            settings.block_size = Some(parse_option(arg, args_iter.next())?);
+        } else if arg == "-t" || arg == "--tail" {
+            let value = option_value(arg, args_iter.next())?;
+            settings.tail = Some(match value.parse::<f32>() {
+                Ok(seconds) if seconds >= 0.0 => seconds,
+                _ => {
+                    return Err(Box::new(ArgumentError(format!(
+                        "expected a number of seconds for '{arg}' but got '{value}'"
+                    ))))
+                }
+            });
         } else {
             usage();
-            return Err(Box::new(ArgumentError(String::from("unknown argument"))));
+            return Err(Box::new(ArgumentError(format!("unknown argument '{arg}'"))));
+        }
+    }
+
+    let file_options_given = settings.output_path.is_some()
+        || settings.file_format.is_some()
+        || settings.sample_format.is_some();
+
+    if file_options_given {
+        match output_target {
+            OutputTarget::None => output_target = OutputTarget::File,
+            OutputTarget::Dac => {
+                usage();
+                return Err(Box::new(ArgumentError(String::from(
+                    "--output, --format and --sample-format can't be used with --dac",
+                ))));
+            }
+            OutputTarget::File => (),
         }
     }
 
+    // check the format before compiling so a long render doesn't fail at the end
+    if output_target == OutputTarget::File {
+        settings
+            .file_format()
+            .format_flags(settings.sample_format)
+            .map_err(|err| ArgumentError(err.to_string()))?;
+    }
+
     let code = fs::read_to_string(file_path)?;
     // let code = include_str!("../examples/wav_player.ral").to_string();
     compiler::compiler::compile_and_run(
         code,
         String::from(file_path.to_str().unwrap()),
         output_target,
+        settings,
     )
 }
 
+fn option_value<'a>(option: &str, value: Option<&'a String>) -> Result<&'a String, ArgumentError> {
+    value.ok_or_else(|| ArgumentError(format!("expected a value after '{option}'")))
+}
+
+/// Parses the value following an option as a number greater than 0.
+fn parse_option<T>(option: &str, value: Option<&String>) -> Result<T, ArgumentError>
+where
+    T: FromStr + PartialOrd + Default,
+{
+    let value = option_value(option, value)?;
+    match value.parse::<T>() {
+        Ok(number) if number > T::default() => Ok(number),
+        _ => Err(ArgumentError(format!(
+            "expected a positive whole number for '{option}' but got '{value}'"
+        ))),
+    }
+}
+
 fn usage() {
-    println!("Usage: ral <file_path>");
+    println!("Usage: ral <file_path> [options]");
+    println!();
+    println!("Options:");
+    println!("    --dac                    Play the performance on the default output device");
+    println!("    --file                   Render the performance to a file");
+    println!("    -o, --output <path>      Path of the rendered file, implies --file (default: test.wav)");
+    println!("    -f, --format <format>    File format: wav, aiff, flac, ogg, opus or mp3, implies --file (default: from the output path, or wav)");
+    println!("    -s, --sample-format <f>  Sample format: pcm16, pcm24, pcm32 or float, implies --file (default: float, or pcm24 for flac)");
+    println!("    -r, --sample-rate <hz>   Sample rate (default: 48000, or the device's highest rate with --dac)");
+    println!("    -c, --channels <n>       Number of output channels (default: 2, or the device's first config with --dac)");
+    println!("    -b, --block-size <n>     Samples per block (default: sample rate / 100, or the device's default with --dac)");
+    println!("    -t, --tail <seconds>     Time to keep performing after the score ends (default: 2 with always on instruments, otherwise 0)");
+    println!("    -h, --help               Show this message");
 }
@@ -1,3 +1,4 @@
+pub mod functions;
 pub mod instrument;
 pub mod ops;
 pub mod value;
@@ -0,0 +1,263 @@
+use crate::{
+    audio::shared_audio_buffer::SharedAudioBuffer,
+    runtime::{
+        instrument::VariableType,
+        value::{Value, ValueType},
+    },
+};
+
+pub type BuiltinFunction = fn(&[Value]) -> Value;
+
+/// Return type for functions that always produce a Float from numbers,
+/// or Audio if any argument is Audio, otherwise Control if any argument is a Control.
+pub fn float_return_type(arg_types: &[VariableType]) -> Option<VariableType> {
+    if !arg_types.iter().all(|arg_type| {
+        arg_type.is_number()
+            || *arg_type == VariableType::Audio
+            || *arg_type == VariableType::Control
+    }) {
+        None
+    } else if arg_types.contains(&VariableType::Audio) {
+        Some(VariableType::Audio)
+    } else if arg_types.contains(&VariableType::Control) {
+        Some(VariableType::Control)
+    } else {
+        Some(VariableType::Float)
+    }
+}
+
+/// Return type for functions that keep Ints as Ints when every argument is an Int.
+pub fn number_return_type(arg_types: &[VariableType]) -> Option<VariableType> {
+    if arg_types
+        .iter()
+        .all(|arg_type| *arg_type == VariableType::Int)
+    {
+        Some(VariableType::Int)
+    } else {
+        float_return_type(arg_types)
+    }
+}
+
+/// Return type for reading a table at an index, Audio or Control if the index is.
+pub fn table_read_return_type(arg_types: &[VariableType]) -> Option<VariableType> {
+    if arg_types[0] != VariableType::Table {
+        None
+    } else if arg_types[1] == VariableType::Audio || arg_types[1] == VariableType::Control {
+        Some(arg_types[1])
+    } else if arg_types[1].is_number() {
+        Some(VariableType::Float)
+    } else {
+        None
+    }
+}
+
+pub fn table_len_return_type(arg_types: &[VariableType]) -> Option<VariableType> {
+    if arg_types[0] == VariableType::Table {
+        Some(VariableType::Int)
+    } else {
+        None
+    }
+}
+
+pub fn sin(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].sin())
+}
+
+pub fn cos(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].cos())
+}
+
+pub fn tan(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].tan())
+}
+
+pub fn tanh(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].tanh())
+}
+
+pub fn exp(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].exp())
+}
+
+pub fn log(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].ln())
+}
+
+pub fn sqrt(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].sqrt())
+}
+
+pub fn pow(args: &[Value]) -> Value {
+    element_wise(args, |values| values[0].powf(values[1]))
+}
+
+pub fn abs(args: &[Value]) -> Value {
+    if all_ints(args) {
+        Value::int(args[0].get_int().abs())
+    } else {
+        element_wise(args, |values| values[0].abs())
+    }
+}
+
+pub fn floor(args: &[Value]) -> Value {
+    if all_ints(args) {
+        args[0].clone()
+    } else {
+        element_wise(args, |values| values[0].floor())
+    }
+}
+
+pub fn ceil(args: &[Value]) -> Value {
+    if all_ints(args) {
+        args[0].clone()
+    } else {
+        element_wise(args, |values| values[0].ceil())
+    }
+}
+
+pub fn round(args: &[Value]) -> Value {
+    if all_ints(args) {
+        args[0].clone()
+    } else {
+        element_wise(args, |values| values[0].round())
+    }
+}
+
+pub fn min(args: &[Value]) -> Value {
+    if all_ints(args) {
+        Value::int(args[0].get_int().min(args[1].get_int()))
+    } else {
+        element_wise(args, |values| values[0].min(values[1]))
+    }
+}
+
+pub fn max(args: &[Value]) -> Value {
+    if all_ints(args) {
+        Value::int(args[0].get_int().max(args[1].get_int()))
+    } else {
+        element_wise(args, |values| values[0].max(values[1]))
+    }
+}
+
+pub fn clamp(args: &[Value]) -> Value {
+    if all_ints(args) {
+        Value::int(
+            args[0]
+                .get_int()
+                .max(args[1].get_int())
+                .min(args[2].get_int()),
+        )
+    } else {
+        element_wise(args, |values| values[0].max(values[1]).min(values[2]))
+    }
+}
+
+pub fn tablei(args: &[Value]) -> Value {
+    let table = args[0].get_table();
+    element_wise(&args[1..], |values| table.read(values[0]))
+}
+
+pub fn tablelen(args: &[Value]) -> Value {
+    Value::int(args[0].get_table().len() as i64)
+}
+
+fn all_ints(args: &[Value]) -> bool {
+    args.iter().all(|arg| arg.value_type() == ValueType::Int)
+}
+
+/// Applies the function to each sample if any of the arguments are Audio, with Controls interpolated across the block.
+/// Otherwise Controls have it applied at both ends of the block, and numbers to themselves.
+/// Always creates a new buffer so the arguments are left untouched.
+fn element_wise<F>(args: &[Value], function: F) -> Value
+where
+    F: Fn(&[f32]) -> f32,
+{
+    let mut values = vec![0.0; args.len()];
+
+    if let Some(audio) = args.iter().find(|arg| arg.value_type() == ValueType::Audio) {
+        let buffer_size = audio.get_audio().buffer_size();
+        let args: Vec<Value> = args
+            .iter()
+            .map(|arg| match arg.value_type() {
+                ValueType::Control => arg.upsample(buffer_size),
+                _ => arg.clone(),
+            })
+            .collect();
+
+        let mut buffer = SharedAudioBuffer::new(1, buffer_size);
+        for sample in 0..buffer_size {
+            for (value, arg) in values.iter_mut().zip(&args) {
+                *value = arg.get_sample(sample);
+            }
+            buffer.set_sample(0, sample, function(&values));
+        }
+        Value::audio(buffer)
+    } else if args
+        .iter()
+        .any(|arg| arg.value_type() == ValueType::Control)
+    {
+        for (value, arg) in values.iter_mut().zip(args) {
+            *value = arg.control_range().0;
+        }
+        let start = function(&values);
+
+        for (value, arg) in values.iter_mut().zip(args) {
+            *value = arg.control_range().1;
+        }
+        Value::control(start, function(&values))
+    } else {
+        for (value, arg) in values.iter_mut().zip(args) {
+            *value = arg.get_sample(0);
+        }
+        Value::float(function(&values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_return_types() {
+        assert_eq!(
+            float_return_type(&[VariableType::Control]),
+            Some(VariableType::Control)
+        );
+        assert_eq!(
+            number_return_type(&[
+                VariableType::Control,
+                VariableType::Int,
+                VariableType::Float
+            ]),
+            Some(VariableType::Control)
+        );
+        assert_eq!(
+            number_return_type(&[VariableType::Control, VariableType::Audio]),
+            Some(VariableType::Audio)
+        );
+        assert_eq!(float_return_type(&[VariableType::Bool]), None);
+    }
+
+    #[test]
+    fn control_is_applied_at_both_ends() {
+        let result = clamp(&[Value::control(-0.5, 2.0), Value::int(0), Value::int(1)]);
+        assert_eq!(result.value_type(), ValueType::Control);
+        assert_eq!(result.get_control(), (0.0, 1.0));
+
+        let result = sqrt(&[Value::control(4.0, 9.0)]);
+        assert_eq!(result.get_control(), (2.0, 3.0));
+    }
+
+    #[test]
+    fn control_with_audio_is_interpolated() {
+        let mut buffer = SharedAudioBuffer::new(1, 4);
+        for sample in 0..4 {
+            buffer.set_sample(0, sample, 1.0);
+        }
+
+        let result = max(&[Value::control(0.0, 4.0), Value::audio(buffer)]);
+        assert_eq!(result.value_type(), ValueType::Audio);
+        let samples: Vec<f32> = (0..4).map(|sample| result.get_sample(sample)).collect();
+        assert_eq!(samples, [1.0, 1.0, 2.0, 3.0]);
+    }
+}
@@ -1,14 +1,16 @@
 use core::num;
-use std::fmt;
+use std::{cmp::Ordering, fmt};
 
 use colored::Colorize;
 
 use crate::{
     audio::{
         audio_buffer::AudioBuffer,
-        components::component::{Component, ComponentType, StreamInfo}, shared_audio_buffer::SharedAudioBuffer,
+        components::component::{Component, ComponentType, StreamInfo},
+        shared_audio_buffer::SharedAudioBuffer,
+        table::Table,
     },
-    runtime::ops::Op,
+    runtime::ops::{LoopCounter, Op},
     runtime::value::Value,
 };
 
@@ -30,11 +32,14 @@ struct FunctionEventInstance {
     // this leaks right now, but maybe that's fine?
     ops: &'static Vec<Op>,
     components: Vec<Box<dyn Component>>,
+    num_locals: usize,
 }
 
 #[derive(Clone)]
 pub struct Instrument {
     instrument_name: String,
+    // always on instruments run for the whole performance instead of being triggered by the score
+    always_on: bool,
     variables: Vec<InstrumentVariable>,
     init_func: Function,
     perf_func: Function,
@@ -53,6 +58,8 @@ pub struct InstrumentEventInstance {
     perf_args: &'static Vec<Value>,
     duration_samples: usize,
     sample_counter: usize,
+    // where in the current buffer the event starts, only non zero in the event's first buffer
+    block_offset: usize,
     max_amps: f32,
 }
 
@@ -60,34 +67,90 @@ pub struct InstrumentEventInstance {
 struct InstrumentVariable {
     variable_name: String,
     variable_type: VariableType,
+    // locals go out of scope at the end of the block they were declared in, but keep their slot
+    in_scope: bool,
 }
 
 impl VariableType {
+    pub fn is_number(&self) -> bool {
+        *self == VariableType::Float || *self == VariableType::Int
+    }
+
     pub fn can_factor_with(&self, other: VariableType) -> bool {
         match self {
-            VariableType::Audio => other != VariableType::String,
-            VariableType::Float => other == VariableType::Float || other == VariableType::Int,
-            VariableType::Int => other == VariableType::Float || other == VariableType::Int,
-            VariableType::String => false,
+            VariableType::Audio | VariableType::Control => {
+                other == VariableType::Audio || other == VariableType::Control || other.is_number()
+            }
+            VariableType::Float => other.is_number() || other == VariableType::Control,
+            VariableType::Int => other.is_number() || other == VariableType::Control,
+            VariableType::Bool | VariableType::String | VariableType::Table => false,
         }
     }
 
     pub fn can_sum_with(&self, other: VariableType) -> bool {
         match self {
-            VariableType::Audio => other != VariableType::String,
-            VariableType::Float => other == VariableType::Float || other == VariableType::Int,
-            VariableType::Int => other == VariableType::Float || other == VariableType::Int,
+            VariableType::Audio | VariableType::Control => {
+                other == VariableType::Audio || other == VariableType::Control || other.is_number()
+            }
+            VariableType::Float => other.is_number() || other == VariableType::Control,
+            VariableType::Int => other.is_number() || other == VariableType::Control,
+            VariableType::Bool | VariableType::Table => false,
+            VariableType::String => other == VariableType::String,
+        }
+    }
+
+    /// The type of an arithmetic expression, Controls are upsampled when combined with Audio
+    /// and numbers combined with a Control vary over the block along with it.
+    pub fn arithmetic_result(&self, other: VariableType) -> VariableType {
+        if *self == VariableType::Audio || other == VariableType::Audio {
+            VariableType::Audio
+        } else if *self == VariableType::Control || other == VariableType::Control {
+            VariableType::Control
+        } else {
+            *self
+        }
+    }
+
+    /// The type of a division or remainder, which is an arithmetic result except that an Int
+    /// divided by a Float is a Float, since a fractional divisor can't be truncated to an Int.
+    pub fn division_result(&self, other: VariableType) -> VariableType {
+        if *self == VariableType::Int && other == VariableType::Float {
+            VariableType::Float
+        } else {
+            self.arithmetic_result(other)
+        }
+    }
+
+    pub fn can_compare_with(&self, other: VariableType) -> bool {
+        (self.is_number() || *self == VariableType::Control)
+            && (other.is_number() || other == VariableType::Control)
+    }
+
+    pub fn can_equate_with(&self, other: VariableType) -> bool {
+        match self {
+            VariableType::Audio | VariableType::Control => false,
+            VariableType::Bool => other == VariableType::Bool,
+            VariableType::Float => other.is_number(),
+            VariableType::Int => other.is_number(),
             VariableType::String => other == VariableType::String,
+            VariableType::Table => false,
         }
     }
+
+    pub fn can_negate(&self) -> bool {
+        *self == VariableType::Audio || *self == VariableType::Control || self.is_number()
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum VariableType {
     Audio,
+    Bool,
+    Control,
     Float,
     Int,
     String,
+    Table,
 }
 
 impl Function {
@@ -101,31 +164,58 @@ impl Function {
         }
     }
 
-    fn finalise(&mut self) {
+    fn finalise(&mut self, sample_rate: u32) {
         self.final_ops = Some(Box::leak(Box::new(self.ops.clone())));
+        // events get copies of these, so they start out ready to use
+        for component in self.components.iter_mut() {
+            component.prepare(sample_rate);
+        }
     }
 
     fn create_event_instance(&self) -> FunctionEventInstance {
         FunctionEventInstance {
             ops: self.final_ops.unwrap(),
             components: self.components.clone(),
+            num_locals: self.locals.len(),
+        }
+    }
+
+    fn get_local(&self, variable_name: &String) -> Option<usize> {
+        self.locals
+            .iter()
+            .position(|variable| variable.in_scope && &variable.variable_name == variable_name)
+    }
+
+    fn end_scope(&mut self, first_local: usize) {
+        for local in self.locals.iter_mut().skip(first_local) {
+            local.in_scope = false;
+        }
+    }
+
+    fn patch_jump(&mut self, op_index: usize, target: usize) {
+        match &mut self.ops[op_index] {
+            Op::Jump(jump_target)
+            | Op::JumpIfFalse(jump_target)
+            | Op::RangeCheck(_, _, jump_target) => *jump_target = target,
+            op => panic!("Cannot patch jump target of {op:?}"),
         }
     }
 }
 
 impl Instrument {
-    pub fn new(instrument_name: String) -> Self {
+    pub fn new(instrument_name: String, always_on: bool) -> Self {
         Instrument {
             instrument_name,
+            always_on,
             variables: Vec::<InstrumentVariable>::new(),
             init_func: Function::new(),
             perf_func: Function::new(),
         }
     }
 
-    pub fn finalise(&mut self) {
-        self.init_func.finalise();
-        self.perf_func.finalise();
+    pub fn finalise(&mut self, sample_rate: u32) {
+        self.init_func.finalise(sample_rate);
+        self.perf_func.finalise(sample_rate);
     }
 
     pub fn create_event_instance(
@@ -143,6 +233,7 @@ impl Instrument {
             perf_args,
             duration_samples,
             sample_counter: 0,
+            block_offset: 0,
             max_amps: 0.0,
         }
     }
@@ -151,10 +242,14 @@ impl Instrument {
         &self.instrument_name
     }
 
+    pub fn always_on(&self) -> bool {
+        self.always_on
+    }
+
     pub fn print_ops(&self) {
         fn print_ops_inner(ops: &Vec<Op>) {
-            for op in ops {
-                println!("\t\t{op:?}");
+            for (index, op) in ops.iter().enumerate() {
+                println!("\t\t{index}: {op:?}");
             }
         }
 
@@ -255,17 +350,27 @@ impl Instrument {
     }
 
     pub fn get_local_init_variable(&self, variable_name: &String) -> Option<usize> {
-        self.init_func
-            .locals
-            .iter()
-            .position(|variable| &variable.variable_name == variable_name)
+        self.init_func.get_local(variable_name)
     }
 
     pub fn get_local_perf_variable(&self, variable_name: &String) -> Option<usize> {
-        self.perf_func
-            .locals
-            .iter()
-            .position(|variable| &variable.variable_name == variable_name)
+        self.perf_func.get_local(variable_name)
+    }
+
+    pub fn num_init_locals(&self) -> usize {
+        self.init_func.locals.len()
+    }
+
+    pub fn num_perf_locals(&self) -> usize {
+        self.perf_func.locals.len()
+    }
+
+    pub fn end_init_scope(&mut self, first_local: usize) {
+        self.init_func.end_scope(first_local);
+    }
+
+    pub fn end_perf_scope(&mut self, first_local: usize) {
+        self.perf_func.end_scope(first_local);
     }
 
     pub fn get_init_arg(&self, arg_name: &String) -> Option<usize> {
@@ -299,6 +404,30 @@ impl Instrument {
     pub fn emit_perf_op(&mut self, op: Op) {
         self.perf_func.ops.push(op);
     }
+
+    pub fn last_init_op(&self) -> Option<&Op> {
+        self.init_func.ops.last()
+    }
+
+    pub fn last_perf_op(&self) -> Option<&Op> {
+        self.perf_func.ops.last()
+    }
+
+    pub fn num_init_ops(&self) -> usize {
+        self.init_func.ops.len()
+    }
+
+    pub fn num_perf_ops(&self) -> usize {
+        self.perf_func.ops.len()
+    }
+
+    pub fn patch_init_jump(&mut self, op_index: usize, target: usize) {
+        self.init_func.patch_jump(op_index, target);
+    }
+
+    pub fn patch_perf_jump(&mut self, op_index: usize, target: usize) {
+        self.perf_func.patch_jump(op_index, target);
+    }
 }
 
 impl fmt::Display for Instrument {
@@ -312,21 +441,84 @@ impl fmt::Display for Instrument {
 }
 
 impl InstrumentEventInstance {
-    pub fn run_init(&mut self, stream_info: &StreamInfo, buffer_to_fill: &mut AudioBuffer) {
+    /// Sets where in the current buffer the event starts, events are created before the performance so it isn't known then.
+    pub fn set_block_offset(&mut self, block_offset: usize) {
+        self.block_offset = block_offset;
+    }
+
+    pub fn run_init(
+        &mut self,
+        stream_info: &StreamInfo,
+        buffer_to_fill: &mut AudioBuffer,
+        buses: &mut [AudioBuffer],
+    ) {
         println!("INFO: running init for {}", self.instrument_name);
-        self.run_ops(false, stream_info, buffer_to_fill);
+        let stream_info = self.event_stream_info(stream_info);
+        self.resize_audio_members(stream_info.buffer_size);
+        self.run_ops(false, &stream_info, buffer_to_fill, buses);
     }
 
     /// Returns true when the event is over
     #[must_use]
-    pub fn run_perf(&mut self, stream_info: &StreamInfo, buffer_to_fill: &mut AudioBuffer) -> bool {
+    pub fn run_perf(
+        &mut self,
+        stream_info: &StreamInfo,
+        buffer_to_fill: &mut AudioBuffer,
+        buses: &mut [AudioBuffer],
+    ) -> bool {
         // let _timer = Timer::new("Perf func");
-        self.run_ops(true, stream_info, buffer_to_fill);
+        let stream_info = self.event_stream_info(stream_info);
+        if stream_info.buffer_size > 0 {
+            self.resize_audio_members(stream_info.buffer_size);
+            self.run_ops(true, &stream_info, buffer_to_fill, buses);
+        }
+
         self.sample_counter += stream_info.buffer_size;
+        self.block_offset = 0;
         self.sample_counter >= self.duration_samples
     }
 
-    fn run_ops(&mut self, perf: bool, stream_info: &StreamInfo, buffer_to_fill: &mut AudioBuffer) {
+    /// The part of the buffer this event plays in, which is shorter than the whole buffer
+    /// when the event starts or ends part way through it.
+    fn event_stream_info(&self, stream_info: &StreamInfo) -> StreamInfo {
+        StreamInfo {
+            sample_rate: stream_info.sample_rate,
+            buffer_size: (stream_info.buffer_size - self.block_offset)
+                .min(self.duration_samples - self.sample_counter),
+            channels: stream_info.channels,
+            event_duration: self.duration_samples,
+        }
+    }
+
+    /// Audio in member variables is the size of the block it was assigned in, which differs from the next
+    /// when the event starts or ends part way through a block, so it's cut or padded with silence to match.
+    fn resize_audio_members(&mut self, buffer_size: usize) {
+        for variable in self.variables.iter_mut() {
+            if variable.value_type() != ValueType::Audio
+                || variable.get_audio().buffer_size() == buffer_size
+            {
+                continue;
+            }
+
+            let source = variable.get_audio();
+            let mut buffer = SharedAudioBuffer::new(source.channels(), buffer_size);
+            for channel in 0..source.channels() {
+                for sample in 0..buffer_size.min(source.buffer_size()) {
+                    buffer.set_sample(channel, sample, source.get_sample(channel, sample));
+                }
+            }
+
+            *variable = Value::audio(buffer);
+        }
+    }
+
+    fn run_ops(
+        &mut self,
+        perf: bool,
+        stream_info: &StreamInfo,
+        buffer_to_fill: &mut AudioBuffer,
+        buses: &mut [AudioBuffer],
+    ) {
         let func = if perf {
             &mut self.perf_func
         } else {
@@ -334,11 +526,16 @@ impl InstrumentEventInstance {
         };
 
         let args = if perf { self.perf_args } else { self.init_args };
+        let offset = self.block_offset;
 
         let mut stack = Vec::<Value>::new();
-        let mut locals = Vec::<Value>::new();
+        let mut locals = vec![Value::default(); func.num_locals];
+
+        let mut ip = 0;
+        while ip < func.ops.len() {
+            let op = &func.ops[ip];
+            ip += 1;
 
-        for op in func.ops {
             match op {
                 Op::AssignLocal(index) => {
                     locals[*index] = stack.pop().unwrap();
@@ -346,49 +543,69 @@ impl InstrumentEventInstance {
                 Op::AssignMember(index) => {
                     self.variables[*index] = stack.pop().unwrap();
                 }
-                Op::CallComponent(index) => {
-                    let arg_count = func.components[*index].arg_count();
-                    let component_type = func.components[*index].component_type();
-                    let mut args = vec![Value::default(); arg_count];
-                    for i in 0..arg_count {
-                        args[arg_count - i - 1] = stack.pop().unwrap();
-                    }
-
-                    match component_type {
-                        ComponentType::Generator => {
-                            let output = func.components[*index].process(stream_info, args);
-                            for buffer in output {
-                                stack.push(buffer);
-                            }
-                        }
-                    }
+                Op::CallComponent(index, arg_count) => {
+                    call_component(
+                        &mut func.components[*index],
+                        *arg_count,
+                        &mut stack,
+                        stream_info,
+                    );
+                }
+                Op::CallLoopComponent(first_index, arg_count, counters) => {
+                    let index = first_index + loop_instance(counters, &locals);
+                    call_component(
+                        &mut func.components[index],
+                        *arg_count,
+                        &mut stack,
+                        stream_info,
+                    );
                 }
-                Op::DeclareLocal(num_locals) => {
+                Op::CallFunction(info) => {
+                    let args = stack.split_off(stack.len() - info.arg_count);
+                    stack.push((info.function)(&args));
+                }
+                Op::DeclareLocal(first_local, num_locals) => {
                     if stack.len() < *num_locals {
                         eprintln!("WARNING: trying to assign to {num_locals} locals but only {} values output by expression, ignoring excess locals", stack.len());
-
                     } else if *num_locals < stack.len() {
                         eprintln!("WARNING: trying to assign to {num_locals} locals but {} values output by expression, ignoring excess values", stack.len());
                     }
-                    
+
                     // there will definitely be 1 thing on the stack
                     // need to know its type in case we need to fill excess values
                     let value_type = stack[0].value_type();
                     for i in 0..*num_locals {
-                        if i < stack.len() {
-                            locals.push(stack[i].clone());
+                        locals[first_local + i] = if i < stack.len() {
+                            stack[i].clone()
                         } else {
-                            locals.push(match value_type {
-                                ValueType::Audio => Value::audio(SharedAudioBuffer::new(1, buffer_to_fill.buffer_size())),
+                            match value_type {
+                                ValueType::Audio => {
+                                    Value::audio(SharedAudioBuffer::new(1, stream_info.buffer_size))
+                                }
+                                ValueType::Bool => Value::bool(false),
+                                ValueType::Control => Value::control(0.0, 0.0),
                                 ValueType::Int => Value::int(0),
                                 ValueType::Float => Value::float(0.0),
                                 ValueType::String => Value::string("".to_string()),
-                            });
-                        }
+                                ValueType::Table => Value::table(Table::empty()),
+                            }
+                        };
                     }
-                    
+
                     stack.clear();
                 }
+                Op::Increment(index) => {
+                    let value = locals[*index].get_int();
+                    locals[*index] = Value::int(value + 1);
+                }
+                Op::Jump(target) => {
+                    ip = *target;
+                }
+                Op::JumpIfFalse(target) => {
+                    if !stack.pop().unwrap().get_bool() {
+                        ip = *target;
+                    }
+                }
                 Op::LoadArg(index) => {
                     stack.push(args[*index].clone());
                 }
@@ -401,15 +618,37 @@ impl InstrumentEventInstance {
                 Op::LoadMember(index) => {
                     stack.push(self.variables[*index].clone());
                 }
-                Op::Output => {                    
+                Op::Output => {
                     for (channel, buffer) in stack.iter().enumerate() {
                         if channel >= buffer_to_fill.channels() {
                             eprintln!("WARNING: excess outputs ignored");
                             break;
                         }
 
-                        for sample in 0..buffer_to_fill.buffer_size() {
-                            buffer_to_fill.add_sample(channel, sample, buffer.get_audio().get_sample(0, sample));
+                        for sample in 0..stream_info.buffer_size {
+                            buffer_to_fill.add_sample(
+                                channel,
+                                offset + sample,
+                                buffer.get_audio().get_sample(0, sample),
+                            );
+                        }
+                    }
+
+                    stack.clear();
+                }
+                Op::OutputChannel => {
+                    let buffer = stack.pop().unwrap();
+                    // channels count from 1 like the speakers they're going to
+                    let channel = stack.pop().unwrap().get_int();
+                    if channel < 1 || channel as usize > buffer_to_fill.channels() {
+                        eprintln!("WARNING: output channel {channel} doesn't exist, there are {} channels", buffer_to_fill.channels());
+                    } else {
+                        for sample in 0..stream_info.buffer_size {
+                            buffer_to_fill.add_sample(
+                                channel as usize - 1,
+                                offset + sample,
+                                buffer.get_audio().get_sample(0, sample),
+                            );
                         }
                     }
                 }
@@ -427,6 +666,47 @@ impl InstrumentEventInstance {
                 Op::PrintLnEmpty => {
                     println!();
                 }
+                Op::RangeCheck(counter, end, target) => {
+                    if locals[*counter].get_int() >= locals[*end].get_int() {
+                        ip = *target;
+                    }
+                }
+                Op::Receive(index, channels) => {
+                    let bus = &buses[*index];
+                    for channel in 0..*channels {
+                        // channels the device didn't give the bus are silent
+                        let mut buffer = SharedAudioBuffer::new(1, stream_info.buffer_size);
+                        if channel < bus.channels() {
+                            for sample in 0..stream_info.buffer_size {
+                                buffer.set_sample(
+                                    0,
+                                    sample,
+                                    bus.get_sample(channel, offset + sample),
+                                );
+                            }
+                        }
+                        stack.push(Value::audio(buffer));
+                    }
+                }
+                Op::Send(index) => {
+                    let bus = &mut buses[*index];
+                    for (channel, buffer) in stack.iter().enumerate() {
+                        if channel >= bus.channels() {
+                            eprintln!("WARNING: excess sends ignored");
+                            break;
+                        }
+
+                        for sample in 0..stream_info.buffer_size {
+                            bus.add_sample(
+                                channel,
+                                offset + sample,
+                                buffer.get_audio().get_sample(0, sample),
+                            );
+                        }
+                    }
+
+                    stack.clear();
+                }
                 Op::Add => {
                     let rhs = stack.pop().unwrap();
                     let lhs = stack.pop().unwrap();
@@ -437,6 +717,11 @@ impl InstrumentEventInstance {
                     let lhs = stack.pop().unwrap();
                     stack.push(lhs / rhs);
                 }
+                Op::Modulo => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(lhs % rhs);
+                }
                 Op::Multiply => {
                     let rhs = stack.pop().unwrap();
                     let lhs = stack.pop().unwrap();
@@ -447,6 +732,64 @@ impl InstrumentEventInstance {
                     let lhs = stack.pop().unwrap();
                     stack.push(lhs - rhs);
                 }
+                Op::Upsample => {
+                    let value = stack.pop().unwrap();
+                    stack.push(value.upsample(stream_info.buffer_size));
+                }
+                Op::Negate => {
+                    let value = stack.pop().unwrap();
+                    stack.push(-value);
+                }
+                Op::Not => {
+                    let value = stack.pop().unwrap();
+                    stack.push(!value);
+                }
+                Op::And => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(lhs.get_bool() && rhs.get_bool()));
+                }
+                Op::Or => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(lhs.get_bool() || rhs.get_bool()));
+                }
+                Op::Equal => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(lhs.compare(&rhs) == Some(Ordering::Equal)));
+                }
+                Op::NotEqual => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(lhs.compare(&rhs) != Some(Ordering::Equal)));
+                }
+                Op::Greater => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(lhs.compare(&rhs) == Some(Ordering::Greater)));
+                }
+                Op::GreaterEqual => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(matches!(
+                        lhs.compare(&rhs),
+                        Some(Ordering::Greater | Ordering::Equal)
+                    )));
+                }
+                Op::Less => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(lhs.compare(&rhs) == Some(Ordering::Less)));
+                }
+                Op::LessEqual => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(Value::bool(matches!(
+                        lhs.compare(&rhs),
+                        Some(Ordering::Less | Ordering::Equal)
+                    )));
+                }
             }
         }
     }
@@ -466,6 +809,40 @@ impl InstrumentVariable {
         InstrumentVariable {
             variable_name,
             variable_type,
+            in_scope: true,
         }
     }
 }
+
+fn call_component(
+    component: &mut Box<dyn Component>,
+    arg_count: usize,
+    stack: &mut Vec<Value>,
+    stream_info: &StreamInfo,
+) {
+    let args = stack.split_off(stack.len() - arg_count);
+    match component.component_type() {
+        ComponentType::Generator => {
+            stack.append(&mut component.process(stream_info, args));
+        }
+        ComponentType::Processor { audio_outputs, .. } => {
+            // the compiler relies on processors always leaving their declared number of outputs on the stack
+            let mut output = component.process(stream_info, args);
+            output.resize_with(audio_outputs, || {
+                Value::audio(SharedAudioBuffer::new(1, stream_info.buffer_size))
+            });
+            stack.append(&mut output);
+        }
+    }
+}
+
+/// Which of a component's instances the current iterations of the loops around it use,
+/// counting through the innermost loop fastest.
+fn loop_instance(counters: &[LoopCounter], locals: &[Value]) -> usize {
+    counters.iter().fold(0, |instance, counter| {
+        // the counter can be assigned to inside the loop, so keep it to the loop's instances
+        let iteration = (locals[counter.local].get_int() - counter.start)
+            .clamp(0, counter.iterations.max(1) as i64 - 1);
+        instance * counter.iterations + iteration as usize
+    })
+}
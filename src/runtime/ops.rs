@@ -1,22 +1,59 @@
-use super::value::Value;
+use super::{value::Value, vm::FunctionInfo};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Op {
     Add,
+    And,
     AssignLocal(usize),
     AssignMember(usize),
-    CallComponent(usize),
-    DeclareLocal(usize),
+    // index of the component, number of inputs
+    CallComponent(usize, usize),
+    CallFunction(&'static FunctionInfo),
+    // index of the component's first instance, number of inputs, and the for loops around the call,
+    // outermost first, which choose the instance so each iteration has its own
+    CallLoopComponent(usize, usize, Vec<LoopCounter>),
+    // index of the first local, number of locals
+    DeclareLocal(usize, usize),
     Divide,
+    Equal,
+    Greater,
+    GreaterEqual,
+    Increment(usize),
+    Jump(usize),
+    JumpIfFalse(usize),
+    Less,
+    LessEqual,
     LoadArg(usize),
     LoadConstant(Value),
     LoadLocal(usize),
     LoadMember(usize),
+    Modulo,
     Multiply,
+    Negate,
+    Not,
+    NotEqual,
+    Or,
     Output,
+    // writes the Audio on top of the stack to the channel below it
+    OutputChannel,
     Print,
     PrintEmpty,
     PrintLn,
     PrintLnEmpty,
+    // jumps to the target if the counter local is not less than the end local
+    RangeCheck(usize, usize, usize),
+    // index of the bus and the number of channels to read from it, one per local being declared
+    Receive(usize, usize),
+    Send(usize),
     Subtract,
+    // turns the Control on top of the stack into Audio for a component input
+    Upsample,
+}
+
+/// The counter of a for loop with literal bounds, so components called inside it can have one instance per iteration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoopCounter {
+    pub local: usize,
+    pub start: i64,
+    pub iterations: usize,
 }
@@ -1,26 +1,34 @@
 use std::{
+    cmp::Ordering,
     fmt,
-    mem::{ManuallyDrop, size_of},
-    ops::{Add, Div, Mul, Sub},
+    mem::{size_of, ManuallyDrop},
+    ops::{Add, Div, Mul, Neg, Not, Rem, Sub},
 };
 
-use crate::audio::shared_audio_buffer::SharedAudioBuffer;
+use crate::audio::{shared_audio_buffer::SharedAudioBuffer, table::Table};
 
 const SIZE: usize = size_of::<Value>();
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ValueType {
     Audio,
+    Bool,
+    Control,
     Int,
     Float,
     String,
+    Table,
 }
 
 union Data {
+    boolean: bool,
     int: i64,
     float: f32,
     string: ManuallyDrop<Box<String>>,
     audio: ManuallyDrop<SharedAudioBuffer>,
+    // the values at the start of this block and the start of the next one
+    control: [f32; 2],
+    table: &'static Table,
 }
 
 pub struct Value {
@@ -36,6 +44,13 @@ impl Value {
         }
     }
 
+    pub fn bool(value: bool) -> Self {
+        Value {
+            value_type: ValueType::Bool,
+            value: Data { boolean: value },
+        }
+    }
+
     pub fn int(value: i64) -> Self {
         Value {
             value_type: ValueType::Int,
@@ -61,10 +76,30 @@ impl Value {
         }
     }
 
+    pub fn control(start: f32, end: f32) -> Self {
+        Value {
+            value_type: ValueType::Control,
+            value: Data {
+                control: [start, end],
+            },
+        }
+    }
+
+    pub fn table(value: &'static Table) -> Self {
+        Value {
+            value_type: ValueType::Table,
+            value: Data { table: value },
+        }
+    }
+
     pub fn value_type(&self) -> ValueType {
         self.value_type
     }
 
+    pub fn get_bool(&self) -> bool {
+        unsafe { self.value.boolean }
+    }
+
     pub fn get_int(&self) -> i64 {
         unsafe { self.value.int }
     }
@@ -73,6 +108,36 @@ impl Value {
         unsafe { self.value.float }
     }
 
+    pub fn get_control(&self) -> (f32, f32) {
+        let [start, end] = unsafe { self.value.control };
+        (start, end)
+    }
+
+    /// Linearly interpolates a Control across a block to get Audio without steps at the block boundaries.
+    pub fn upsample(&self, buffer_size: usize) -> Value {
+        let (start, end) = self.get_control();
+        let mut buffer = SharedAudioBuffer::new(1, buffer_size);
+        for sample in 0..buffer_size {
+            buffer.set_sample(
+                0,
+                sample,
+                start + (end - start) * sample as f32 / buffer_size as f32,
+            );
+        }
+
+        Value::audio(buffer)
+    }
+
+    /// The values at the start and end of the block, where Ints and Floats are the same at both.
+    pub fn control_range(&self) -> (f32, f32) {
+        match self.value_type {
+            ValueType::Control => self.get_control(),
+            ValueType::Float => (self.get_float(), self.get_float()),
+            ValueType::Int => (self.get_int() as f32, self.get_int() as f32),
+            _ => unreachable!(),
+        }
+    }
+
     pub fn get_audio(&self) -> &SharedAudioBuffer {
         unsafe { &self.value.audio }
     }
@@ -84,6 +149,53 @@ impl Value {
     pub fn get_string(&self) -> &String {
         unsafe { self.value.string.as_ref() }
     }
+
+    /// Table members start out as Int 0 like every other member, so this gives an empty table until one is assigned.
+    pub fn get_table(&self) -> &'static Table {
+        match self.value_type {
+            ValueType::Table => unsafe { self.value.table },
+            _ => Table::empty(),
+        }
+    }
+
+    /// Gets the value of the first channel at the given sample for Audio, or the number itself for Ints and Floats.
+    /// Controls give the value at the start of the block, the compiler upsamples them before they reach Audio inputs.
+    pub fn get_sample(&self, sample: usize) -> f32 {
+        match self.value_type {
+            ValueType::Audio => self.get_audio().get_sample(0, sample),
+            ValueType::Control => self.get_control().0,
+            ValueType::Float => self.get_float(),
+            ValueType::Int => self.get_int() as f32,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Compares two values, allowing Ints and Floats to be compared with each other.
+    /// Controls are compared by their value at the start of the block, like `get_sample`.
+    /// Returns None for values that can't be compared.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self.value_type, other.value_type) {
+            (ValueType::Control, ValueType::Control | ValueType::Int | ValueType::Float)
+            | (ValueType::Int | ValueType::Float, ValueType::Control) => {
+                self.control_range().0.partial_cmp(&other.control_range().0)
+            }
+            (ValueType::Int, ValueType::Int) => self.get_int().partial_cmp(&other.get_int()),
+            (ValueType::Int, ValueType::Float) => {
+                (self.get_int() as f32).partial_cmp(&other.get_float())
+            }
+            (ValueType::Float, ValueType::Int) => {
+                self.get_float().partial_cmp(&(other.get_int() as f32))
+            }
+            (ValueType::Float, ValueType::Float) => {
+                self.get_float().partial_cmp(&other.get_float())
+            }
+            (ValueType::Bool, ValueType::Bool) => self.get_bool().partial_cmp(&other.get_bool()),
+            (ValueType::String, ValueType::String) => {
+                self.get_string().partial_cmp(other.get_string())
+            }
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for Value {
@@ -93,19 +205,51 @@ impl PartialEq for Value {
         } else {
             match self.value_type {
                 ValueType::Audio => false, // TODO
+                ValueType::Control => false,
+                ValueType::Bool => unsafe { self.value.boolean == other.value.boolean },
                 ValueType::Int => unsafe { self.value.int == other.value.int },
                 ValueType::Float => unsafe { self.value.float == other.value.float },
                 ValueType::String => unsafe {
                     self.value.string.as_ref() == other.value.string.as_ref()
                 },
+                ValueType::Table => std::ptr::eq(self.get_table(), other.get_table()),
             }
         }
     }
 }
+/// Applies an arithmetic op where either side is a Control. Against Audio the Control is upsampled to match,
+/// otherwise the op is applied at both ends of the block so the result is still a Control.
+fn control_arithmetic(
+    lhs: Value,
+    rhs: Value,
+    op: fn(Value, Value) -> Value,
+    scalar_op: fn(f32, f32) -> f32,
+) -> Value {
+    match (lhs.value_type, rhs.value_type) {
+        (ValueType::Control, ValueType::Audio) => {
+            let buffer_size = rhs.get_audio().buffer_size();
+            op(lhs.upsample(buffer_size), rhs)
+        }
+        (ValueType::Audio, ValueType::Control) => {
+            let buffer_size = lhs.get_audio().buffer_size();
+            op(lhs, rhs.upsample(buffer_size))
+        }
+        _ => {
+            let (lhs_start, lhs_end) = lhs.control_range();
+            let (rhs_start, rhs_end) = rhs.control_range();
+            Value::control(scalar_op(lhs_start, rhs_start), scalar_op(lhs_end, rhs_end))
+        }
+    }
+}
+
 impl Mul for Value {
     type Output = Value;
     // TODO: allow different types - audio * float, string * int etc...
     fn mul(self, rhs: Self) -> Self::Output {
+        if self.value_type == ValueType::Control || rhs.value_type == ValueType::Control {
+            return control_arithmetic(self, rhs, <Value as Mul>::mul, |lhs, rhs| lhs * rhs);
+        }
+
         match self.value_type {
             ValueType::Audio => {
                 let mut buffer = self.get_audio().clone();
@@ -133,38 +277,106 @@ impl Mul for Value {
                         _ => unreachable!(),
                     },
             ),
-            ValueType::String => unreachable!(),
+            ValueType::Bool | ValueType::Control | ValueType::String | ValueType::Table => {
+                unreachable!()
+            }
         }
     }
 }
 impl Div for Value {
     type Output = Value;
     fn div(self, rhs: Self) -> Self::Output {
+        if self.value_type == ValueType::Control || rhs.value_type == ValueType::Control {
+            return control_arithmetic(self, rhs, <Value as Div>::div, quotient);
+        }
+
         match self.value_type {
-            ValueType::Audio => unreachable!(),
-            ValueType::Int => Value::int(
-                self.get_int()
-                    / match rhs.value_type {
-                        ValueType::Int => rhs.get_int(),
-                        ValueType::Float => rhs.get_float() as i64,
-                        _ => unreachable!(),
-                    },
-            ),
-            ValueType::Float => Value::float(
-                self.get_float()
-                    / match rhs.value_type {
-                        ValueType::Int => rhs.get_int() as f32,
-                        ValueType::Float => rhs.get_float(),
-                        _ => unreachable!(),
-                    },
-            ),
-            ValueType::String => unreachable!(),
+            ValueType::Audio => {
+                let mut buffer = self.get_audio().clone();
+                match rhs.value_type {
+                    ValueType::Audio => buffer.divide_by(rhs.get_audio()),
+                    ValueType::Float | ValueType::Int => {
+                        buffer.apply_gain(quotient(1.0, rhs.get_sample(0)))
+                    }
+                    _ => unreachable!(),
+                }
+                Value::audio(buffer)
+            }
+            ValueType::Int => match rhs.value_type {
+                ValueType::Int => {
+                    Value::int(self.get_int().checked_div(rhs.get_int()).unwrap_or(0))
+                }
+                ValueType::Float => Value::float(quotient(self.get_int() as f32, rhs.get_float())),
+                _ => unreachable!(),
+            },
+            ValueType::Float => Value::float(quotient(self.get_float(), rhs.get_sample(0))),
+            ValueType::Bool | ValueType::Control | ValueType::String | ValueType::Table => {
+                unreachable!()
+            }
+        }
+    }
+}
+/// Division that gives 0 for a divisor of 0 instead of producing infinity or NaN in the audio thread.
+fn quotient(lhs: f32, rhs: f32) -> f32 {
+    if rhs == 0.0 {
+        0.0
+    } else {
+        lhs / rhs
+    }
+}
+
+/// Remainder that gives 0 for a divisor of 0 instead of panicking or producing NaN in the audio thread.
+fn remainder(lhs: f32, rhs: f32) -> f32 {
+    if rhs == 0.0 {
+        0.0
+    } else {
+        lhs % rhs
+    }
+}
+
+impl Rem for Value {
+    type Output = Value;
+    fn rem(self, rhs: Self) -> Self::Output {
+        if self.value_type == ValueType::Control || rhs.value_type == ValueType::Control {
+            return control_arithmetic(self, rhs, <Value as Rem>::rem, remainder);
+        }
+
+        match self.value_type {
+            ValueType::Audio => {
+                let source = self.get_audio();
+                let mut buffer = SharedAudioBuffer::new(source.channels(), source.buffer_size());
+                for channel in 0..source.channels() {
+                    for sample in 0..source.buffer_size() {
+                        buffer.set_sample(
+                            channel,
+                            sample,
+                            remainder(source.get_sample(channel, sample), rhs.get_sample(sample)),
+                        );
+                    }
+                }
+                Value::audio(buffer)
+            }
+            ValueType::Int => match rhs.value_type {
+                ValueType::Int => {
+                    Value::int(self.get_int().checked_rem(rhs.get_int()).unwrap_or(0))
+                }
+                ValueType::Float => Value::float(remainder(self.get_int() as f32, rhs.get_float())),
+                _ => unreachable!(),
+            },
+            ValueType::Float => Value::float(remainder(self.get_float(), rhs.get_sample(0))),
+            ValueType::Bool | ValueType::Control | ValueType::String | ValueType::Table => {
+                unreachable!()
+            }
         }
     }
 }
 impl Add for Value {
     type Output = Value;
     fn add(self, rhs: Self) -> Self::Output {
+        if self.value_type == ValueType::Control || rhs.value_type == ValueType::Control {
+            return control_arithmetic(self, rhs, <Value as Add>::add, |lhs, rhs| lhs + rhs);
+        }
+
         match self.value_type {
             ValueType::Audio => {
                 let mut buffer = self.get_audio().clone();
@@ -193,12 +405,17 @@ impl Add for Value {
                     },
             ),
             ValueType::String => Value::string(self.get_string().to_owned() + rhs.get_string()),
+            ValueType::Bool | ValueType::Control | ValueType::Table => unreachable!(),
         }
     }
 }
 impl Sub for Value {
     type Output = Value;
     fn sub(self, rhs: Self) -> Self::Output {
+        if self.value_type == ValueType::Control || rhs.value_type == ValueType::Control {
+            return control_arithmetic(self, rhs, <Value as Sub>::sub, |lhs, rhs| lhs - rhs);
+        }
+
         match self.value_type {
             ValueType::Audio => {
                 let mut buffer = self.get_audio().clone();
@@ -226,7 +443,39 @@ impl Sub for Value {
                         _ => unreachable!(),
                     },
             ),
-            ValueType::String => unreachable!(),
+            ValueType::Bool | ValueType::Control | ValueType::String | ValueType::Table => {
+                unreachable!()
+            }
+        }
+    }
+}
+
+impl Neg for Value {
+    type Output = Value;
+    fn neg(self) -> Self::Output {
+        match self.value_type {
+            ValueType::Audio => {
+                let source = self.get_audio();
+                let mut buffer = SharedAudioBuffer::new(source.channels(), source.buffer_size());
+                buffer.subtract_from(source);
+                Value::audio(buffer)
+            }
+            ValueType::Int => Value::int(-self.get_int()),
+            ValueType::Float => Value::float(-self.get_float()),
+            ValueType::Control => {
+                let (start, end) = self.get_control();
+                Value::control(-start, -end)
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+impl Not for Value {
+    type Output = Value;
+    fn not(self) -> Self::Output {
+        match self.value_type {
+            ValueType::Bool => Value::bool(!self.get_bool()),
+            _ => unreachable!(),
         }
     }
 }
@@ -252,6 +501,12 @@ impl Clone for Value {
                     ValueType::Audio => Data {
                         audio: self.value.audio.clone(),
                     },
+                    ValueType::Bool => Data {
+                        boolean: self.value.boolean,
+                    },
+                    ValueType::Control => Data {
+                        control: self.value.control,
+                    },
                     ValueType::Int => Data {
                         int: self.value.int,
                     },
@@ -261,6 +516,9 @@ impl Clone for Value {
                     ValueType::String => Data {
                         string: self.value.string.clone(),
                     },
+                    ValueType::Table => Data {
+                        table: self.value.table,
+                    },
                 },
             }
         }
@@ -281,9 +539,12 @@ impl fmt::Display for Value {
         unsafe {
             match self.value_type {
                 ValueType::Audio => fmt::Display::fmt(&*self.value.audio, f),
+                ValueType::Bool => fmt::Display::fmt(&self.value.boolean, f),
+                ValueType::Control => fmt::Display::fmt(&self.value.control[0], f),
                 ValueType::Int => fmt::Display::fmt(&self.value.int, f),
                 ValueType::Float => fmt::Display::fmt(&self.value.float, f),
                 ValueType::String => fmt::Display::fmt(&*self.value.string, f),
+                ValueType::Table => fmt::Display::fmt(self.value.table, f),
             }
         }
     }
@@ -294,10 +555,140 @@ impl fmt::Debug for Value {
         unsafe {
             match self.value_type {
                 ValueType::Audio => fmt::Debug::fmt(&*self.value.audio, f),
+                ValueType::Bool => fmt::Debug::fmt(&self.value.boolean, f),
+                ValueType::Control => fmt::Debug::fmt(&self.value.control, f),
                 ValueType::Int => fmt::Debug::fmt(&self.value.int, f),
                 ValueType::Float => fmt::Debug::fmt(&self.value.float, f),
                 ValueType::String => fmt::Debug::fmt(&*self.value.string, f),
+                ValueType::Table => fmt::Debug::fmt(self.value.table, f),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn int_remainder_int() {
+        let result = Value::int(7) % Value::int(3);
+        assert_eq!(result.value_type(), ValueType::Int);
+        assert_eq!(result.get_int(), 1);
+    }
+
+    #[test]
+    fn int_remainder_fractional_float() {
+        let result = Value::int(5) % Value::float(0.5);
+        assert_eq!(result.value_type(), ValueType::Float);
+        assert_eq!(result.get_float(), 0.0);
+
+        let result = Value::int(5) % Value::float(1.5);
+        assert_eq!(result.value_type(), ValueType::Float);
+        assert_eq!(result.get_float(), 0.5);
+    }
+
+    #[test]
+    fn float_remainder_int() {
+        let result = Value::float(5.5) % Value::int(2);
+        assert_eq!(result.value_type(), ValueType::Float);
+        assert_eq!(result.get_float(), 1.5);
+    }
+
+    #[test]
+    fn remainder_by_zero_is_zero() {
+        assert_eq!((Value::int(5) % Value::int(0)).get_int(), 0);
+        assert_eq!((Value::int(5) % Value::float(0.0)).get_float(), 0.0);
+        assert_eq!((Value::float(5.0) % Value::float(0.0)).get_float(), 0.0);
+        assert_eq!((Value::int(i64::MIN) % Value::int(-1)).get_int(), 0);
+    }
+
+    #[test]
+    fn audio_remainder() {
+        let mut buffer = SharedAudioBuffer::new(1, 2);
+        buffer.set_sample(0, 0, 2.5);
+        buffer.set_sample(0, 1, 3.0);
+
+        let result = Value::audio(buffer) % Value::float(2.0);
+        assert_eq!(result.get_sample(0), 0.5);
+        assert_eq!(result.get_sample(1), 1.0);
+
+        let result = Value::audio(SharedAudioBuffer::new(1, 2)) % Value::int(0);
+        assert_eq!(result.get_sample(0), 0.0);
+    }
+
+    #[test]
+    fn control_remainder() {
+        let result = Value::control(5.0, 7.0) % Value::int(4);
+        assert_eq!(result.value_type(), ValueType::Control);
+        assert_eq!(result.get_control(), (1.0, 3.0));
+
+        let result = Value::control(5.0, 7.0) % Value::float(0.0);
+        assert_eq!(result.get_control(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn int_divided_by_fractional_float() {
+        let result = Value::int(5) / Value::float(0.5);
+        assert_eq!(result.value_type(), ValueType::Float);
+        assert_eq!(result.get_float(), 10.0);
+
+        let result = Value::int(7) / Value::int(2);
+        assert_eq!(result.value_type(), ValueType::Int);
+        assert_eq!(result.get_int(), 3);
+    }
+
+    #[test]
+    fn division_by_zero_is_zero() {
+        assert_eq!((Value::int(5) / Value::int(0)).get_int(), 0);
+        assert_eq!((Value::int(5) / Value::float(0.0)).get_float(), 0.0);
+        assert_eq!((Value::float(5.0) / Value::int(0)).get_float(), 0.0);
+        assert_eq!((Value::int(i64::MIN) / Value::int(-1)).get_int(), 0);
+        assert_eq!(
+            (Value::control(1.0, 2.0) / Value::float(0.0)).get_control(),
+            (0.0, 0.0)
+        );
+
+        let ramp = || {
+            let mut buffer = SharedAudioBuffer::new(1, 2);
+            buffer.set_sample(0, 0, 1.0);
+            buffer.set_sample(0, 1, 2.0);
+            Value::audio(buffer)
+        };
+
+        let mut divisor = SharedAudioBuffer::new(1, 2);
+        divisor.set_sample(0, 1, 4.0);
+        let result = ramp() / Value::audio(divisor);
+        assert_eq!(result.get_sample(0), 0.0);
+        assert_eq!(result.get_sample(1), 0.5);
+
+        let result = ramp() / Value::int(0);
+        assert_eq!(result.get_sample(1), 0.0);
+    }
+
+    #[test]
+    fn control_compares_at_block_start() {
+        let control = Value::control(0.25, 0.75);
+        assert_eq!(control.compare(&Value::float(0.5)), Some(Ordering::Less));
+        assert_eq!(Value::int(0).compare(&control), Some(Ordering::Less));
+        assert_eq!(
+            control.compare(&Value::control(0.25, 0.0)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(control.compare(&Value::bool(true)), None);
+    }
+
+    #[test]
+    fn control_with_audio_is_interpolated() {
+        let mut buffer = SharedAudioBuffer::new(1, 4);
+        for sample in 0..4 {
+            buffer.set_sample(0, sample, 1.0);
+        }
+
+        let result = Value::control(0.0, 4.0) * Value::audio(buffer);
+        assert_eq!(result.value_type(), ValueType::Audio);
+        for sample in 0..4 {
+            assert_eq!(result.get_sample(sample), sample as f32);
+        }
+    }
+}
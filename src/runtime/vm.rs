@@ -1,62 +1,453 @@
 use cpal::SupportedStreamConfig;
 use phf::phf_map;
-use sndfile::{OpenOptions, WriteOptions, SndFileIO};
 
 use crate::{
     audio::{
         self,
         audio_buffer::AudioBuffer,
         components::{
-            component::{Component, StreamInfo},
+            component::{Component, ComponentType, StreamInfo},
             generators::{
-                adsr::Adsr, generator::Generator, mtof::Mtof, noise::Noise, oscil::Oscil,
-                padsr::Padsr, sample::Sample,
+                adsr::Adsr,
+                expseg::Expseg,
+                fm4::Fm4,
+                fm_op::FmOp,
+                generator::Generator,
+                lag::Lag,
+                line::Line,
+                linseg::Linseg,
+                mtof::Mtof,
+                noise::Noise,
+                oscil::Oscil,
+                padsr::Padsr,
+                portamento::Portamento,
+                sample::{self, Sample, SampleFile},
+                sampler::Sampler,
+                table_oscil::TableOscil,
+                transeg::Transeg,
+                vco::Vco,
+            },
+            processors::{
+                biquad::{Biquad, FilterType},
+                delay::{Delay, DelayType},
+                peak::Peak,
+                processor::Processor,
+                reverb::Reverb,
             },
         },
+        file_format::{Ditherer, FileFormat, SampleFormat},
+        file_writer::FileWriter,
+        resample::ResampleQuality,
+        table::Table,
     },
+    runtime::functions::{self, BuiltinFunction},
     runtime::instrument::{Instrument, InstrumentEventInstance, VariableType},
     runtime::value::Value,
 };
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     error::Error,
+    fmt,
+    io::Write,
+    path::PathBuf,
+    sync::atomic::{self, AtomicBool},
     time::{Duration, Instant},
 };
 
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+const DEFAULT_CHANNELS: u16 = 2;
+const DEFAULT_CONTROL_RATE: u32 = 100;
+// seconds rendered after the score ends when there are always on instruments, so reverb and delay tails aren't cut off
+const DEFAULT_TAIL: f32 = 2.0;
+// the extension is replaced to match the file format
+const DEFAULT_OUTPUT_FILE: &str = "test.wav";
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, atomic::Ordering::SeqCst);
+}
+
 static COMPONENTS: phf::Map<&'static str, ComponentInfo> = phf_map! {
     "Noise" => ComponentInfo {
         factory: || Box::new(Noise::new()),
         input_types: &Noise::INPUT_TYPES,
         output_type: Noise::OUTPUT_TYPE,
+        component_type: Noise::COMPONENT_TYPE,
+        repeated_inputs: Noise::REPEATED_INPUTS,
+        optional_inputs: Noise::OPTIONAL_INPUTS,
+        constant_inputs: Noise::CONSTANT_INPUTS,
+        sample_inputs: Noise::SAMPLE_INPUTS,
+    },
+    "Fm4" => ComponentInfo {
+        factory: || Box::new(Fm4::new()),
+        input_types: &Fm4::INPUT_TYPES,
+        output_type: Fm4::OUTPUT_TYPE,
+        component_type: Fm4::COMPONENT_TYPE,
+        repeated_inputs: Fm4::REPEATED_INPUTS,
+        optional_inputs: Fm4::OPTIONAL_INPUTS,
+        constant_inputs: Fm4::CONSTANT_INPUTS,
+        sample_inputs: Fm4::SAMPLE_INPUTS,
+    },
+    "FmOp" => ComponentInfo {
+        factory: || Box::new(FmOp::new()),
+        input_types: &FmOp::INPUT_TYPES,
+        output_type: FmOp::OUTPUT_TYPE,
+        component_type: FmOp::COMPONENT_TYPE,
+        repeated_inputs: FmOp::REPEATED_INPUTS,
+        optional_inputs: FmOp::OPTIONAL_INPUTS,
+        constant_inputs: FmOp::CONSTANT_INPUTS,
+        sample_inputs: FmOp::SAMPLE_INPUTS,
+    },
+    "Line" => ComponentInfo {
+        factory: || Box::new(Line::new()),
+        input_types: &Line::INPUT_TYPES,
+        output_type: Line::OUTPUT_TYPE,
+        component_type: Line::COMPONENT_TYPE,
+        repeated_inputs: Line::REPEATED_INPUTS,
+        optional_inputs: Line::OPTIONAL_INPUTS,
+        constant_inputs: Line::CONSTANT_INPUTS,
+        sample_inputs: Line::SAMPLE_INPUTS,
+    },
+    "Linseg" => ComponentInfo {
+        factory: || Box::new(Linseg::new()),
+        input_types: &Linseg::INPUT_TYPES,
+        output_type: Linseg::OUTPUT_TYPE,
+        component_type: Linseg::COMPONENT_TYPE,
+        repeated_inputs: Linseg::REPEATED_INPUTS,
+        optional_inputs: Linseg::OPTIONAL_INPUTS,
+        constant_inputs: Linseg::CONSTANT_INPUTS,
+        sample_inputs: Linseg::SAMPLE_INPUTS,
+    },
+    "Expseg" => ComponentInfo {
+        factory: || Box::new(Expseg::new()),
+        input_types: &Expseg::INPUT_TYPES,
+        output_type: Expseg::OUTPUT_TYPE,
+        component_type: Expseg::COMPONENT_TYPE,
+        repeated_inputs: Expseg::REPEATED_INPUTS,
+        optional_inputs: Expseg::OPTIONAL_INPUTS,
+        constant_inputs: Expseg::CONSTANT_INPUTS,
+        sample_inputs: Expseg::SAMPLE_INPUTS,
+    },
+    "Transeg" => ComponentInfo {
+        factory: || Box::new(Transeg::new()),
+        input_types: &Transeg::INPUT_TYPES,
+        output_type: Transeg::OUTPUT_TYPE,
+        component_type: Transeg::COMPONENT_TYPE,
+        repeated_inputs: Transeg::REPEATED_INPUTS,
+        optional_inputs: Transeg::OPTIONAL_INPUTS,
+        constant_inputs: Transeg::CONSTANT_INPUTS,
+        sample_inputs: Transeg::SAMPLE_INPUTS,
+    },
+    "Lag" => ComponentInfo {
+        factory: || Box::new(Lag::new()),
+        input_types: &Lag::INPUT_TYPES,
+        output_type: Lag::OUTPUT_TYPE,
+        component_type: Lag::COMPONENT_TYPE,
+        repeated_inputs: Lag::REPEATED_INPUTS,
+        optional_inputs: Lag::OPTIONAL_INPUTS,
+        constant_inputs: Lag::CONSTANT_INPUTS,
+        sample_inputs: Lag::SAMPLE_INPUTS,
+    },
+    "Portamento" => ComponentInfo {
+        factory: || Box::new(Portamento::new()),
+        input_types: &Portamento::INPUT_TYPES,
+        output_type: Portamento::OUTPUT_TYPE,
+        component_type: Portamento::COMPONENT_TYPE,
+        repeated_inputs: Portamento::REPEATED_INPUTS,
+        optional_inputs: Portamento::OPTIONAL_INPUTS,
+        constant_inputs: Portamento::CONSTANT_INPUTS,
+        sample_inputs: Portamento::SAMPLE_INPUTS,
     },
     "Oscil" => ComponentInfo {
         factory: || Box::new(Oscil::new()),
         input_types: &Oscil::INPUT_TYPES,
         output_type: Oscil::OUTPUT_TYPE,
+        component_type: Oscil::COMPONENT_TYPE,
+        repeated_inputs: Oscil::REPEATED_INPUTS,
+        optional_inputs: Oscil::OPTIONAL_INPUTS,
+        constant_inputs: Oscil::CONSTANT_INPUTS,
+        sample_inputs: Oscil::SAMPLE_INPUTS,
     },
     "Mtof" => ComponentInfo {
         factory: || Box::new(Mtof{}),
         input_types: &Mtof::INPUT_TYPES,
         output_type: Mtof::OUTPUT_TYPE,
+        component_type: Mtof::COMPONENT_TYPE,
+        repeated_inputs: Mtof::REPEATED_INPUTS,
+        optional_inputs: Mtof::OPTIONAL_INPUTS,
+        constant_inputs: Mtof::CONSTANT_INPUTS,
+        sample_inputs: Mtof::SAMPLE_INPUTS,
     },
     "Adsr" => ComponentInfo {
         factory: || Box::new(Adsr::new()),
         input_types: &Adsr::INPUT_TYPES,
         output_type: Adsr::OUTPUT_TYPE,
+        component_type: Adsr::COMPONENT_TYPE,
+        repeated_inputs: Adsr::REPEATED_INPUTS,
+        optional_inputs: Adsr::OPTIONAL_INPUTS,
+        constant_inputs: Adsr::CONSTANT_INPUTS,
+        sample_inputs: Adsr::SAMPLE_INPUTS,
     },
     "Padsr" => ComponentInfo {
         factory: || Box::new(Padsr::new()),
         input_types: &Padsr::INPUT_TYPES,
         output_type: Padsr::OUTPUT_TYPE,
+        component_type: Padsr::COMPONENT_TYPE,
+        repeated_inputs: Padsr::REPEATED_INPUTS,
+        optional_inputs: Padsr::OPTIONAL_INPUTS,
+        constant_inputs: Padsr::CONSTANT_INPUTS,
+        sample_inputs: Padsr::SAMPLE_INPUTS,
+    },
+    "TableOscil" => ComponentInfo {
+        factory: || Box::new(TableOscil::new()),
+        input_types: &TableOscil::INPUT_TYPES,
+        output_type: TableOscil::OUTPUT_TYPE,
+        component_type: TableOscil::COMPONENT_TYPE,
+        repeated_inputs: TableOscil::REPEATED_INPUTS,
+        optional_inputs: TableOscil::OPTIONAL_INPUTS,
+        constant_inputs: TableOscil::CONSTANT_INPUTS,
+        sample_inputs: TableOscil::SAMPLE_INPUTS,
+    },
+    "Vco" => ComponentInfo {
+        factory: || Box::new(Vco::new()),
+        input_types: &Vco::INPUT_TYPES,
+        output_type: Vco::OUTPUT_TYPE,
+        component_type: Vco::COMPONENT_TYPE,
+        repeated_inputs: Vco::REPEATED_INPUTS,
+        optional_inputs: Vco::OPTIONAL_INPUTS,
+        constant_inputs: Vco::CONSTANT_INPUTS,
+        sample_inputs: Vco::SAMPLE_INPUTS,
     },
     "WavPlayer" => ComponentInfo {
         factory: || Box::new(Sample::new()),
         input_types: &Sample::INPUT_TYPES,
         output_type: Sample::OUTPUT_TYPE,
-    }
+        component_type: Sample::COMPONENT_TYPE,
+        repeated_inputs: Sample::REPEATED_INPUTS,
+        optional_inputs: Sample::OPTIONAL_INPUTS,
+        constant_inputs: Sample::CONSTANT_INPUTS,
+        sample_inputs: Sample::SAMPLE_INPUTS,
+    },
+    "Sampler" => ComponentInfo {
+        factory: || Box::new(Sampler::new()),
+        input_types: &Sampler::INPUT_TYPES,
+        output_type: Sampler::OUTPUT_TYPE,
+        component_type: Sampler::COMPONENT_TYPE,
+        repeated_inputs: Sampler::REPEATED_INPUTS,
+        optional_inputs: Sampler::OPTIONAL_INPUTS,
+        constant_inputs: Sampler::CONSTANT_INPUTS,
+        sample_inputs: Sampler::SAMPLE_INPUTS,
+    },
+    "Lpf" => ComponentInfo {
+        factory: || Box::new(Biquad::new(FilterType::LowPass)),
+        input_types: &Biquad::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Biquad::COMPONENT_TYPE,
+        repeated_inputs: Biquad::REPEATED_INPUTS,
+        optional_inputs: Biquad::OPTIONAL_INPUTS,
+        constant_inputs: Biquad::CONSTANT_INPUTS,
+        sample_inputs: Biquad::SAMPLE_INPUTS,
+    },
+    "Hpf" => ComponentInfo {
+        factory: || Box::new(Biquad::new(FilterType::HighPass)),
+        input_types: &Biquad::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Biquad::COMPONENT_TYPE,
+        repeated_inputs: Biquad::REPEATED_INPUTS,
+        optional_inputs: Biquad::OPTIONAL_INPUTS,
+        constant_inputs: Biquad::CONSTANT_INPUTS,
+        sample_inputs: Biquad::SAMPLE_INPUTS,
+    },
+    "Bpf" => ComponentInfo {
+        factory: || Box::new(Biquad::new(FilterType::BandPass)),
+        input_types: &Biquad::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Biquad::COMPONENT_TYPE,
+        repeated_inputs: Biquad::REPEATED_INPUTS,
+        optional_inputs: Biquad::OPTIONAL_INPUTS,
+        constant_inputs: Biquad::CONSTANT_INPUTS,
+        sample_inputs: Biquad::SAMPLE_INPUTS,
+    },
+    "Notch" => ComponentInfo {
+        factory: || Box::new(Biquad::new(FilterType::Notch)),
+        input_types: &Biquad::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Biquad::COMPONENT_TYPE,
+        repeated_inputs: Biquad::REPEATED_INPUTS,
+        optional_inputs: Biquad::OPTIONAL_INPUTS,
+        constant_inputs: Biquad::CONSTANT_INPUTS,
+        sample_inputs: Biquad::SAMPLE_INPUTS,
+    },
+    "Peak" => ComponentInfo {
+        factory: || Box::new(Peak::new()),
+        input_types: &Peak::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Peak::COMPONENT_TYPE,
+        repeated_inputs: Peak::REPEATED_INPUTS,
+        optional_inputs: Peak::OPTIONAL_INPUTS,
+        constant_inputs: Peak::CONSTANT_INPUTS,
+        sample_inputs: Peak::SAMPLE_INPUTS,
+    },
+    "Delay" => ComponentInfo {
+        factory: || Box::new(Delay::new(DelayType::Delay)),
+        input_types: &Delay::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Delay::COMPONENT_TYPE,
+        repeated_inputs: Delay::REPEATED_INPUTS,
+        optional_inputs: Delay::OPTIONAL_INPUTS,
+        constant_inputs: Delay::CONSTANT_INPUTS,
+        sample_inputs: Delay::SAMPLE_INPUTS,
+    },
+    "Comb" => ComponentInfo {
+        factory: || Box::new(Delay::new(DelayType::Comb)),
+        input_types: &Delay::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Delay::COMPONENT_TYPE,
+        repeated_inputs: Delay::REPEATED_INPUTS,
+        optional_inputs: Delay::OPTIONAL_INPUTS,
+        constant_inputs: Delay::CONSTANT_INPUTS,
+        sample_inputs: Delay::SAMPLE_INPUTS,
+    },
+    "Allpass" => ComponentInfo {
+        factory: || Box::new(Delay::new(DelayType::Allpass)),
+        input_types: &Delay::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Delay::COMPONENT_TYPE,
+        repeated_inputs: Delay::REPEATED_INPUTS,
+        optional_inputs: Delay::OPTIONAL_INPUTS,
+        constant_inputs: Delay::CONSTANT_INPUTS,
+        sample_inputs: Delay::SAMPLE_INPUTS,
+    },
+    "Reverb" => ComponentInfo {
+        factory: || Box::new(Reverb::new()),
+        input_types: &Reverb::INPUT_TYPES,
+        output_type: VariableType::Audio,
+        component_type: Reverb::COMPONENT_TYPE,
+        repeated_inputs: Reverb::REPEATED_INPUTS,
+        optional_inputs: Reverb::OPTIONAL_INPUTS,
+        constant_inputs: Reverb::CONSTANT_INPUTS,
+        sample_inputs: Reverb::SAMPLE_INPUTS,
+    },
+};
+
+static FUNCTIONS: phf::Map<&'static str, FunctionInfo> = phf_map! {
+    "sin" => FunctionInfo {
+        name: "sin",
+        function: functions::sin,
+        arg_count: 1,
+        return_type: functions::float_return_type,
+    },
+    "cos" => FunctionInfo {
+        name: "cos",
+        function: functions::cos,
+        arg_count: 1,
+        return_type: functions::float_return_type,
+    },
+    "tan" => FunctionInfo {
+        name: "tan",
+        function: functions::tan,
+        arg_count: 1,
+        return_type: functions::float_return_type,
+    },
+    "tanh" => FunctionInfo {
+        name: "tanh",
+        function: functions::tanh,
+        arg_count: 1,
+        return_type: functions::float_return_type,
+    },
+    "exp" => FunctionInfo {
+        name: "exp",
+        function: functions::exp,
+        arg_count: 1,
+        return_type: functions::float_return_type,
+    },
+    "log" => FunctionInfo {
+        name: "log",
+        function: functions::log,
+        arg_count: 1,
+        return_type: functions::float_return_type,
+    },
+    "sqrt" => FunctionInfo {
+        name: "sqrt",
+        function: functions::sqrt,
+        arg_count: 1,
+        return_type: functions::float_return_type,
+    },
+    "pow" => FunctionInfo {
+        name: "pow",
+        function: functions::pow,
+        arg_count: 2,
+        return_type: functions::float_return_type,
+    },
+    "abs" => FunctionInfo {
+        name: "abs",
+        function: functions::abs,
+        arg_count: 1,
+        return_type: functions::number_return_type,
+    },
+    "floor" => FunctionInfo {
+        name: "floor",
+        function: functions::floor,
+        arg_count: 1,
+        return_type: functions::number_return_type,
+    },
+    "ceil" => FunctionInfo {
+        name: "ceil",
+        function: functions::ceil,
+        arg_count: 1,
+        return_type: functions::number_return_type,
+    },
+    "round" => FunctionInfo {
+        name: "round",
+        function: functions::round,
+        arg_count: 1,
+        return_type: functions::number_return_type,
+    },
+    "min" => FunctionInfo {
+        name: "min",
+        function: functions::min,
+        arg_count: 2,
+        return_type: functions::number_return_type,
+    },
+    "max" => FunctionInfo {
+        name: "max",
+        function: functions::max,
+        arg_count: 2,
+        return_type: functions::number_return_type,
+    },
+    "clamp" => FunctionInfo {
+        name: "clamp",
+        function: functions::clamp,
+        arg_count: 3,
+        return_type: functions::number_return_type,
+    },
+    "tablei" => FunctionInfo {
+        name: "tablei",
+        function: functions::tablei,
+        arg_count: 2,
+        return_type: functions::table_read_return_type,
+    },
+    "tablelen" => FunctionInfo {
+        name: "tablelen",
+        function: functions::tablelen,
+        arg_count: 1,
+        return_type: functions::table_len_return_type,
+    },
 };
 
+#[derive(Debug)]
+pub struct OutputFileError(String);
+
+impl fmt::Display for OutputFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Output file error: {}", self.0)
+    }
+}
+
+impl Error for OutputFileError {}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum OutputTarget {
     Dac,
@@ -64,6 +455,42 @@ pub enum OutputTarget {
     None,
 }
 
+/// Performance settings given on the command line, anything left as None uses the default for the output target.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub block_size: Option<u32>,
+    // blocks per second, only used when no block size is given
+    pub control_rate: Option<u32>,
+    pub output_path: Option<PathBuf>,
+    pub file_format: Option<FileFormat>,
+    pub sample_format: Option<SampleFormat>,
+    // seconds to keep performing after the last score event ends
+    pub tail: Option<f32>,
+}
+
+impl Settings {
+    /// The block size if one was given, otherwise derived from the control rate.
+    pub fn block_size(&self, sample_rate: u32) -> Option<u32> {
+        self.block_size.or_else(|| {
+            self.control_rate
+                .map(|control_rate| (sample_rate / control_rate).max(1))
+        })
+    }
+
+    /// The format given on the command line, or guessed from the output path, defaulting to wav.
+    pub fn file_format(&self) -> FileFormat {
+        self.file_format
+            .or_else(|| {
+                self.output_path
+                    .as_ref()
+                    .and_then(|path| FileFormat::from_path(path))
+            })
+            .unwrap_or(FileFormat::Wav)
+    }
+}
+
 #[derive(PartialEq)]
 pub enum LogLevel {
     Everything,
@@ -75,10 +502,24 @@ pub enum LogLevel {
 pub struct VM {
     instruments: Vec<Instrument>,
     score_events: Vec<ScoreEvent>,
-    sorted_score_events: HashMap<usize, Vec<ScoreEvent>>,
-    active_score_events: Vec<InstrumentEventInstance>,
+    // score events are created in finalise and keyed by their start sample, so starting one on the audio thread
+    // doesn't allocate its components, delay lines or variables
+    sorted_score_events: HashMap<usize, Vec<(usize, InstrumentEventInstance)>>,
+    // active events, including always on instruments, are grouped by instrument index
+    // so they run in the order the instruments were declared
+    active_events: Vec<Vec<InstrumentEventInstance>>,
+    // buses are referred to by index at runtime, the compiler resolves names to indices
+    bus_names: Vec<String>,
+    // allocated in finalise and cleared at the start of each buffer, so the audio thread doesn't allocate them
+    buses: Vec<AudioBuffer>,
+    // files used by sample players, read during compilation so the audio thread never touches the disk
+    sample_files: HashMap<String, SampleFile>,
+    resample_qualities: HashSet<ResampleQuality>,
+    // generated once by the tables block, values refer to them by static reference
+    tables: HashMap<String, &'static Table>,
     sample_counter: usize,
     audio_config: Option<SupportedStreamConfig>,
+    settings: Settings,
     total_perf_time: Duration,
     max_perf_time: Duration,
     perf_count: u32,
@@ -104,8 +545,63 @@ struct ScoreEvent {
 #[derive(Clone)]
 pub struct ComponentInfo {
     pub factory: fn() -> Box<dyn Component>,
-    pub input_types: &'static [VariableType],
+    pub input_types: &'static [&'static [VariableType]],
     pub output_type: VariableType,
+    pub component_type: ComponentType,
+    pub repeated_inputs: usize,
+    pub optional_inputs: usize,
+    // inputs that must be given a literal, which the component sees during compilation
+    pub constant_inputs: &'static [usize],
+    // positions of the file path and resample quality inputs of sample players
+    pub sample_inputs: Option<(usize, usize)>,
+}
+
+impl ComponentInfo {
+    /// The types allowed for the input at this position, going round the repeated inputs past the end.
+    pub fn input_type(&self, position: usize) -> &'static [VariableType] {
+        let len = self.input_types.len();
+        if position < len || self.repeated_inputs == 0 {
+            self.input_types[position.min(len - 1)]
+        } else {
+            self.input_types[len - self.repeated_inputs + (position - len) % self.repeated_inputs]
+        }
+    }
+
+    pub fn accepts_arg_count(&self, arg_count: usize) -> bool {
+        let len = self.input_types.len();
+        (len - self.optional_inputs..=len).contains(&arg_count)
+            || (self.repeated_inputs > 0
+                && arg_count > len
+                && (arg_count - len) % self.repeated_inputs == 0)
+    }
+}
+
+pub struct FunctionInfo {
+    pub name: &'static str,
+    pub function: BuiltinFunction,
+    pub arg_count: usize,
+    pub return_type: fn(&[VariableType]) -> Option<VariableType>,
+}
+
+// functions are compared by name since function pointers aren't guaranteed to be unique
+impl PartialEq for FunctionInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Debug for FunctionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub fn has_function(function_name: &str) -> bool {
+    FUNCTIONS.contains_key(function_name)
+}
+
+pub fn function_info(function_name: &str) -> &'static FunctionInfo {
+    FUNCTIONS.get(function_name).unwrap()
 }
 
 pub fn has_component(component_name: &str) -> bool {
@@ -117,14 +613,20 @@ pub fn component_info(component_name: &str) -> ComponentInfo {
 }
 
 impl VM {
-    pub fn new() -> Self {
+    pub fn new(settings: Settings) -> Self {
         VM {
             instruments: Vec::<Instrument>::new(),
             score_events: Vec::<ScoreEvent>::new(),
-            sorted_score_events: HashMap::<usize, Vec<ScoreEvent>>::new(),
-            active_score_events: Vec::<InstrumentEventInstance>::new(),
+            sorted_score_events: HashMap::<usize, Vec<(usize, InstrumentEventInstance)>>::new(),
+            active_events: Vec::<Vec<InstrumentEventInstance>>::new(),
+            bus_names: Vec::<String>::new(),
+            buses: Vec::<AudioBuffer>::new(),
+            sample_files: HashMap::<String, SampleFile>::new(),
+            resample_qualities: HashSet::<ResampleQuality>::new(),
+            tables: HashMap::<String, &'static Table>::new(),
             sample_counter: 0,
             audio_config: None,
+            settings,
             total_perf_time: Duration::ZERO,
             max_perf_time: Duration::ZERO,
             perf_count: 0,
@@ -135,12 +637,42 @@ impl VM {
         self.instruments.push(instrument);
     }
 
+    /// Reads a file used by a sample player, if it hasn't been already.
+    pub fn load_sample(&mut self, path: &String) -> Result<(), String> {
+        if !self.sample_files.contains_key(path) {
+            self.sample_files
+                .insert(path.clone(), SampleFile::load(path)?);
+        }
+
+        Ok(())
+    }
+
+    pub fn add_resample_quality(&mut self, quality: ResampleQuality) {
+        self.resample_qualities.insert(quality);
+    }
+
+    pub fn add_table(&mut self, table_name: String, table: Table) {
+        self.tables.insert(table_name, Box::leak(Box::new(table)));
+    }
+
+    pub fn table(&self, table_name: &String) -> Option<&'static Table> {
+        self.tables.get(table_name).copied()
+    }
+
     pub fn has_instrument(&self, instrument_name: &String) -> bool {
         self.instruments
             .iter()
             .any(|instrument| instrument.name() == instrument_name)
     }
 
+    pub fn instrument_always_on(&self, instrument_name: &String) -> bool {
+        self.instruments
+            .iter()
+            .find(|instrument| instrument.name() == instrument_name)
+            .unwrap()
+            .always_on()
+    }
+
     pub fn instrument_num_init_args(&self, instrument_name: &String) -> usize {
         self.instruments
             .iter()
@@ -198,6 +730,16 @@ impl VM {
         });
     }
 
+    /// Gets the index of the bus with this name, creating it if no instrument has used it yet.
+    pub fn bus_index(&mut self, bus_name: &String) -> usize {
+        if let Some(index) = self.bus_names.iter().position(|name| name == bus_name) {
+            index
+        } else {
+            self.bus_names.push(bus_name.clone());
+            self.bus_names.len() - 1
+        }
+    }
+
     pub fn print_ops(&self) {
         for instrument in &self.instruments {
             instrument.print_ops();
@@ -208,6 +750,14 @@ impl VM {
         self.audio_config = Some(config);
     }
 
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        &mut self.settings
+    }
+
     pub fn config(&self) -> &SupportedStreamConfig {
         self.audio_config.as_ref().unwrap()
     }
@@ -237,12 +787,43 @@ impl VM {
     }
 
     pub fn finalise(&mut self, sample_rate: cpal::SampleRate) -> f32 {
+        sample::prepare_samples(&self.sample_files, &self.resample_qualities, sample_rate.0);
+
         for instrument in self.instruments.iter_mut() {
-            instrument.finalise();
+            instrument.finalise(sample_rate.0);
+        }
+
+        self.active_events = self
+            .instruments
+            .iter()
+            .map(|_| Vec::<InstrumentEventInstance>::new())
+            .collect();
+
+        let channels = self.config().channels() as usize;
+        let block_size = self
+            .settings
+            .block_size(sample_rate.0)
+            .unwrap_or(sample_rate.0 / DEFAULT_CONTROL_RATE) as usize;
+        self.buses = self
+            .bus_names
+            .iter()
+            .map(|_| AudioBuffer::new(channels, block_size))
+            .collect();
+
+        for (index, instrument) in self.instruments.iter().enumerate() {
+            if instrument.always_on() {
+                let no_args = Box::leak(Box::new(Vec::<Value>::new()));
+                self.active_events[index].push(instrument.create_event_instance(
+                    usize::MAX,
+                    no_args,
+                    no_args,
+                ));
+            }
         }
 
         let sr = sample_rate.0 as f32;
         let mut last_end_sample = 0.0;
+        let mut event_counts = vec![0; self.instruments.len()];
         for event in self.score_events.iter_mut() {
             event.final_init_args = Some(Box::leak(Box::new(event.init_args.clone())));
             event.final_perf_args = Some(Box::leak(Box::new(event.perf_args.clone())));
@@ -253,18 +834,33 @@ impl VM {
                 last_end_sample = end_time;
             }
             // println!("Adding sorted score event at {sample}: {event:?}");
-            if self.sorted_score_events.contains_key(&sample) {
-                self.sorted_score_events
-                    .get_mut(&sample)
-                    .unwrap()
-                    .push(event.clone());
-            } else {
-                self.sorted_score_events.insert(sample, vec![event.clone()]);
-            }
+            let instance = self.instruments[event.instrument_index].create_event_instance(
+                (event.duration * sr) as usize,
+                event.final_init_args.unwrap(),
+                event.final_perf_args.unwrap(),
+            );
+            self.sorted_score_events
+                .entry(sample)
+                .or_default()
+                .push((event.instrument_index, instance));
+            event_counts[event.instrument_index] += 1;
+        }
+
+        // room for every event, so starting one never grows the instrument's active events
+        for (events, count) in self.active_events.iter_mut().zip(event_counts) {
+            events.reserve(count);
         }
 
         // println!("{:?}", self.sorted_score_events);
-        last_end_sample
+        let has_always_on = self
+            .instruments
+            .iter()
+            .any(|instrument| instrument.always_on());
+        let tail = self
+            .settings
+            .tail
+            .unwrap_or(if has_always_on { DEFAULT_TAIL } else { 0.0 });
+        last_end_sample + tail
     }
 
     pub fn get_next_buffer(&mut self, channels: usize, buffer_size: usize) -> AudioBuffer {
@@ -276,31 +872,50 @@ impl VM {
             sample_rate: self.config().sample_rate().0,
             buffer_size,
             channels,
+            event_duration: usize::MAX,
         };
 
-        for _ in 0..buffer_size {
-            if let Some(events) = self.sorted_score_events.get(&self.sample_counter) {
-                for event in events.iter() {
-                    let index = event.instrument_index;
-                    let mut instrument = self.instruments[index].create_event_instance(
-                        (event.duration * self.config().sample_rate().0 as f32) as usize,
-                        event.final_init_args.unwrap(),
-                        event.final_perf_args.unwrap(),
-                    );
-                    instrument.run_init(&stream_info, &mut buffer_to_fill);
-                    self.active_score_events.push(instrument);
+        for bus in self.buses.iter_mut() {
+            // only reallocated if the device doesn't use the block size the buses were made for
+            if bus.channels() != channels || bus.buffer_size() != buffer_size {
+                *bus = AudioBuffer::new(channels, buffer_size);
+            } else {
+                bus.clear();
+            }
+        }
+
+        // only always on instruments are active before the first buffer
+        if self.sample_counter == 0 {
+            for events in self.active_events.iter_mut() {
+                for instrument in events.iter_mut() {
+                    instrument.run_init(&stream_info, &mut buffer_to_fill, &mut self.buses);
+                }
+            }
+        }
+
+        for offset in 0..buffer_size {
+            if let Some(events) = self.sorted_score_events.remove(&self.sample_counter) {
+                for (index, mut instrument) in events {
+                    instrument.set_block_offset(offset);
+                    instrument.run_init(&stream_info, &mut buffer_to_fill, &mut self.buses);
+                    self.active_events[index].push(instrument);
                 }
             }
             self.sample_counter += 1;
         }
 
-        // TODO: instrument execution order
-        let mut i = 0;
-        while i < self.active_score_events.len() {
-            if self.active_score_events[i].run_perf(&stream_info, &mut buffer_to_fill) {
-                self.active_score_events.remove(i);
-            } else {
-                i += 1;
+        // instruments run in declaration order whether they're always on or not, so an instrument sending to a bus
+        // should be declared before any instrument receiving from it. the order of events within an instrument
+        // doesn't matter since they all run the same code, so finished events can be swapped out.
+        // always on instruments never finish
+        for events in self.active_events.iter_mut() {
+            let mut i = 0;
+            while i < events.len() {
+                if events[i].run_perf(&stream_info, &mut buffer_to_fill, &mut self.buses) {
+                    events.swap_remove(i);
+                } else {
+                    i += 1;
+                }
             }
         }
 
@@ -314,73 +929,117 @@ impl VM {
     }
 
     fn write_to_file(&mut self) -> Result<(), Box<dyn Error>> {
-        const SAMPLE_RATE: u32 = 48000;
-        const BUFFER_SIZE: u32 = SAMPLE_RATE / 100;
-        const CHANNELS: u16 = 2;
+        let (sample_rate, channels, block_size) = self.add_file_config();
+        let len = (self.finalise(self.config().sample_rate()) * (sample_rate as f32)) as usize;
 
-        self.add_config(SupportedStreamConfig::new(
-            CHANNELS,
-            cpal::SampleRate(SAMPLE_RATE),
-            cpal::SupportedBufferSize::Range {
-                min: BUFFER_SIZE,
-                max: BUFFER_SIZE,
-            },
-            cpal::SampleFormat::F32,
-        ));
-
-        let len = (self.finalise(self.config().sample_rate()) * (SAMPLE_RATE as f32)) as usize;
-        let path = std::env::current_dir()?.join("test.wav");
-        let mut snd = match OpenOptions::WriteOnly(WriteOptions::new(sndfile::MajorFormat::WAV, sndfile::SubtypeFormat::FLOAT, sndfile::Endian::CPU, 48000, 2)).from_path(path) {
-            Ok(snd) => snd,
-            Err(err) => {
-                panic!("Failed to open file: {:?}", err);
-            }
+        let file_format = self.settings.file_format();
+        let format_flags = file_format.format_flags(self.settings.sample_format)?;
+        let path = match &self.settings.output_path {
+            Some(path) => path.clone(),
+            None => std::env::current_dir()?
+                .join(DEFAULT_OUTPUT_FILE)
+                .with_extension(file_format.extension()),
         };
 
+        let mut writer = FileWriter::create(&path, format_flags, sample_rate, channels as usize)
+            .map_err(OutputFileError)?;
+
+        // the file's header is only finalised when it's dropped, so catch ctrl-c and stop rendering instead of exiting
+        INTERRUPTED.store(false, atomic::Ordering::SeqCst);
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                handle_interrupt as *const () as libc::sighandler_t,
+            );
+        }
+
+        let mut ditherer = Ditherer::new(file_format.sample_format(self.settings.sample_format)?);
+        let mut samples = Vec::<f32>::with_capacity(block_size as usize * channels as usize);
         let mut sample_counter = 0;
-        let mut samples = Vec::<f32>::new();
+        let mut frames_written = 0;
+        let mut last_percent = None;
+
         while sample_counter < len {
-            let buff = self.get_next_buffer(CHANNELS as usize, BUFFER_SIZE as usize);
-            for sample in 0..buff.buffer_size() {
+            if INTERRUPTED.load(atomic::Ordering::SeqCst) {
+                println!();
+                eprintln!("Render interrupted, stopping early");
+                break;
+            }
+
+            let buff = self.get_next_buffer(channels as usize, block_size as usize);
+            // the last block is cut short so the file ends exactly at the end of the score
+            let frames = buff.buffer_size().min(len - sample_counter);
+
+            samples.clear();
+            for sample in 0..frames {
                 for channel in 0..buff.channels() {
                     samples.push(buff.get_sample(channel, sample));
                 }
             }
-            sample_counter += 480;
+
+            ditherer.process(&mut samples);
+
+            match writer.write(samples.as_slice()) {
+                Ok(frames) => frames_written += frames,
+                Err(err) => {
+                    eprintln!("Failed to write to {}: {err}", path.display());
+                    break;
+                }
+            }
+
+            sample_counter += frames;
+
+            let percent = sample_counter * 100 / len;
+            if last_percent != Some(percent) {
+                print!("\rRendering {}: {percent}%", path.display());
+                std::io::stdout().flush()?;
+                last_percent = Some(percent);
+            }
         }
 
-        match snd.write_from_slice(samples.as_slice()) {
-            Ok(len) => println!("{len} samples written to test.wav"),
-            Err(err) => eprintln!("Failed to write to wav: {:?}", err),
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
         }
-        
+
+        println!();
+        println!("{frames_written} frames written to {}", path.display());
+
         Ok(())
     }
 
     fn run_no_output(&mut self) -> Result<(), Box<dyn Error>> {
-        const SAMPLE_RATE: u32 = 48000;
-        const BUFFER_SIZE: u32 = SAMPLE_RATE / 100;
-        const CHANNELS: u16 = 2;
+        let (sample_rate, channels, block_size) = self.add_file_config();
+        let len = (self.finalise(self.config().sample_rate()) * (sample_rate as f32)) as usize;
+
+        let mut sample_counter = 0;
+        while sample_counter < len {
+            self.get_next_buffer(channels as usize, block_size as usize);
+            sample_counter += block_size as usize;
+        }
+
+        Ok(())
+    }
+
+    /// Sets up the config for non real time performances from the settings, returning the sample rate, channels and block size.
+    fn add_file_config(&mut self) -> (u32, u16, u32) {
+        let sample_rate = self.settings.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE);
+        let channels = self.settings.channels.unwrap_or(DEFAULT_CHANNELS);
+        let block_size = self
+            .settings
+            .block_size(sample_rate)
+            .unwrap_or(sample_rate / DEFAULT_CONTROL_RATE);
 
         self.add_config(SupportedStreamConfig::new(
-            CHANNELS,
-            cpal::SampleRate(SAMPLE_RATE),
+            channels,
+            cpal::SampleRate(sample_rate),
             cpal::SupportedBufferSize::Range {
-                min: BUFFER_SIZE,
-                max: BUFFER_SIZE,
+                min: block_size,
+                max: block_size,
             },
             cpal::SampleFormat::F32,
         ));
 
-        let len = (self.finalise(self.config().sample_rate()) * (SAMPLE_RATE as f32)) as usize;
-
-        let mut sample_counter = 0;
-        while sample_counter < len {
-            self.get_next_buffer(CHANNELS as usize, BUFFER_SIZE as usize);
-            sample_counter += 480;
-        }
-
-        Ok(())
+        (sample_rate, channels, block_size)
     }
 }
 
@@ -395,3 +1054,23 @@ impl Drop for VM {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processor_audio_inputs_are_audio() {
+        for (name, info) in COMPONENTS.entries() {
+            if let ComponentType::Processor { audio_inputs, .. } = info.component_type {
+                for position in 0..audio_inputs {
+                    assert_eq!(
+                        info.input_types[position],
+                        &[VariableType::Audio],
+                        "{name} input {position}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -303,7 +303,10 @@ impl<T: Number + fmt::Debug> fmt::Debug for NumberArray<T> {
 impl<T: Number> Clone for NumberArray<T> {
     fn clone(&self) -> Self {
         unsafe {
-            let ptr = alloc(Layout::from_size_align_unchecked(self.len * T::SIZE, T::ALIGNMENT)) as *mut T;
+            let ptr = alloc(Layout::from_size_align_unchecked(
+                self.len * T::SIZE,
+                T::ALIGNMENT,
+            )) as *mut T;
             for i in 0..self.len {
                 *ptr.add(i) = self[i];
             }